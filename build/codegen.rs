@@ -1,27 +1,97 @@
+use std::collections::BTreeMap;
+
 use quote::{format_ident, quote};
 
-use super::config::{Config, OperationConfig, ReaderConfig, WriterConfig};
+use super::config::{
+    ApiKeyLocationConfig, BatchFormatConfig, ChangeDetectionConfig, Config, EnvelopeConfig,
+    FormatConfig, HttpAuthConfig, HttpMethodConfig, KafkaSecurityConfig, NatsJetStreamConfig,
+    OperationConfig, PaginationConfig, PaginationStrategyConfig, PropertyValue, RateLimitConfig,
+    ReaderConfig, RedisCommandConfig, SaslMechanismConfig, SecretConfig, SecurityProtocolConfig,
+    VerificationConfig, WatermarkConfig, WriteModeConfig, WriterConfig,
+};
 
 pub fn generate(config: &Config) -> proc_macro2::TokenStream {
-    let operation_builders: Vec<_> = config.operations.iter().map(gen_operation).collect();
+    let names: Vec<_> = config
+        .operations
+        .iter()
+        .map(OperationConfig::name)
+        .collect();
+    let builder_fns: Vec<_> = (0..config.operations.len())
+        .map(|i| format_ident!("build_operation_{i}"))
+        .collect();
+    let builder_bodies: Vec<_> = config.operations.iter().map(gen_operation).collect();
 
+    // Every reader and writer is built, even after one fails, so that all the
+    // problems in the config are reported in one go.
     quote! {
         use std::time::Duration;
 
         use serde_json::Value;
 
         use courier::Courier;
+        use courier::cli::Args;
         use courier::readers::api::ApiReader;
         use courier::readers::kafka::KafkaReader;
         use courier::writers::kafka::KafkaWriter;
         use courier::operations::*;
 
-        pub fn courier_from_config() -> Courier {
+        type OperationBuilder = fn(&Args, &mut Vec<String>) -> Option<Box<dyn Operation>>;
+
+        /// Builds one reader or writer, recording its error instead of
+        /// stopping at it.
+        fn build_component<T>(
+            component: &str,
+            errors: &mut Vec<String>,
+            build: impl FnOnce() -> anyhow::Result<T>,
+        ) -> Option<T> {
+            match build() {
+                Ok(built) => Some(built),
+                Err(e) => {
+                    errors.push(format!("{component}: {e:#}"));
+                    None
+                }
+            }
+        }
+
+        #(
+            // Each component is wrapped in `Ok(...?)` so its errors are
+            // collected rather than returned.
+            #[allow(unused_variables, clippy::needless_question_mark)]
+            fn #builder_fns(args: &Args, errors: &mut Vec<String>) -> Option<Box<dyn Operation>> {
+                #builder_bodies
+            }
+        )*
+
+        pub fn courier_from_config(args: &Args) -> anyhow::Result<Courier> {
+            let builders: Vec<(&str, OperationBuilder)> = vec![
+                #((#names, #builder_fns)),*
+            ];
+
             let mut operations: Vec<Box<dyn Operation>> = Vec::new();
+            let mut errors: Vec<String> = Vec::new();
+            let mut failed = 0;
 
-            #(#operation_builders)*
+            for (name, build) in builders {
+                let mut operation_errors = Vec::new();
+                match build(args, &mut operation_errors) {
+                    Some(operation) => operations.push(operation),
+                    None => failed += 1,
+                }
+                errors.extend(
+                    operation_errors
+                        .into_iter()
+                        .map(|e| format!("[{name}] {e}")),
+                );
+            }
+
+            if !errors.is_empty() {
+                anyhow::bail!(
+                    "Failed to build {failed} operation(s):\n  {}",
+                    errors.join("\n  ")
+                );
+            }
 
-            Courier::new(operations)
+            Ok(Courier::new(operations))
         }
     }
 }
@@ -33,22 +103,29 @@ fn gen_operation(op: &OperationConfig) -> proc_macro2::TokenStream {
             reader,
             writer,
             interval_secs,
+            write_mode,
         } => {
+            if matches!(write_mode, WriteModeConfig::Individual) {
+                check_batch_format(name, writer);
+            }
             let reader_expr = gen_reader_expr(reader);
             let writer_expr = gen_writer_expr(writer);
+            let write_mode = gen_write_mode(write_mode);
 
             quote! {
-                {
-                    let reader = #reader_expr;
-                    let writer = #writer_expr;
-                    let operation = IntervalOperation::new(
-                        #name,
-                        reader,
-                        writer,
-                        Duration::from_secs(#interval_secs)
-                    );
-                    operations.push(Box::new(operation));
-                }
+                let reader = build_component("reader", errors, || Ok(#reader_expr));
+                let writer = build_component("writer", errors, || Ok(#writer_expr));
+                let (Some(reader), Some(writer)) = (reader, writer) else {
+                    return None;
+                };
+                let operation = IntervalOperation::new(
+                    #name,
+                    reader,
+                    writer,
+                    Duration::from_secs(#interval_secs)
+                )
+                .with_write_mode(#write_mode);
+                Some(Box::new(operation))
             }
         }
         OperationConfig::Stream {
@@ -56,16 +133,18 @@ fn gen_operation(op: &OperationConfig) -> proc_macro2::TokenStream {
             reader,
             writer,
         } => {
+            check_batch_format(name, writer);
             let reader_expr = gen_reader_expr(reader);
             let writer_expr = gen_writer_expr(writer);
 
             quote! {
-                {
-                    let reader = #reader_expr;
-                    let writer = #writer_expr;
-                    let operation = StreamOperation::new(#name, reader, writer);
-                    operations.push(Box::new(operation));
-                }
+                let reader = build_component("reader", errors, || Ok(#reader_expr));
+                let writer = build_component("writer", errors, || Ok(#writer_expr));
+                let (Some(reader), Some(writer)) = (reader, writer) else {
+                    return None;
+                };
+                let operation = StreamOperation::new(#name, reader, writer);
+                Some(Box::new(operation))
             }
         }
         OperationConfig::IntervalFanout {
@@ -73,36 +152,424 @@ fn gen_operation(op: &OperationConfig) -> proc_macro2::TokenStream {
             reader,
             writers,
             interval_secs,
+            write_mode,
         } => {
+            if matches!(write_mode, WriteModeConfig::Individual) {
+                writers
+                    .iter()
+                    .for_each(|writer| check_batch_format(name, writer));
+            }
             let reader_expr = gen_reader_expr(reader);
             let writer_exprs: Vec<_> = writers.iter().map(gen_writer_expr).collect();
+            let writer_vars: Vec<_> = (0..writers.len())
+                .map(|i| format_ident!("writer_{i}"))
+                .collect();
+            let writer_labels: Vec<_> =
+                (1..=writers.len()).map(|i| format!("writer {i}")).collect();
+            let write_mode = gen_write_mode(write_mode);
 
             quote! {
-                {
-                    let reader = #reader_expr;
-                    let mut operation = IntervalFanoutOperation::new(
-                        #name,
-                        reader,
-                        Duration::from_secs(#interval_secs)
-                    );
-
-                    #(
-                        operation.add_writer(#writer_exprs);
-                    )*
-
-                    operations.push(Box::new(operation));
-                }
+                let reader = build_component("reader", errors, || Ok(#reader_expr));
+                #(
+                    let #writer_vars = build_component(#writer_labels, errors, || Ok(#writer_exprs));
+                )*
+                let (Some(reader), #(Some(#writer_vars)),*) = (reader, #(#writer_vars),*) else {
+                    return None;
+                };
+                let mut operation = IntervalFanoutOperation::new(
+                    #name,
+                    reader,
+                    Duration::from_secs(#interval_secs)
+                )
+                .with_write_mode(#write_mode);
+
+                #(
+                    operation.add_writer(#writer_vars);
+                )*
+
+                Some(Box::new(operation))
             }
         }
     }
 }
 
+/// `batch_format` only changes how `write_batch` sends records, so it is
+/// rejected where records are written one at a time.
+fn check_batch_format(name: &str, writer: &WriterConfig) {
+    if let WriterConfig::HttpWriter {
+        batch_format: Some(batch_format),
+        ..
+    } = writer
+        && !matches!(batch_format, BatchFormatConfig::Individual)
+    {
+        panic!("Operation '{name}': batch_format requires write_mode = \"batch\"");
+    }
+}
+
+fn gen_write_mode(write_mode: &WriteModeConfig) -> proc_macro2::TokenStream {
+    match write_mode {
+        WriteModeConfig::Individual => quote! { WriteMode::Individual },
+        WriteModeConfig::Batch => quote! { WriteMode::Batch },
+    }
+}
+
 fn gen_reader_expr(reader: &ReaderConfig) -> proc_macro2::TokenStream {
     match reader {
-        ReaderConfig::ApiReader { url, data_type } => {
+        ReaderConfig::ApiReader {
+            url,
+            data_type,
+            method,
+            headers,
+            query,
+            body,
+            auth,
+            records_path,
+            split_records,
+            pagination,
+            watermark,
+            change_detection,
+            rate_limit,
+            max_retries,
+            format,
+            envelope,
+            connect_timeout_secs,
+            read_timeout_secs,
+            timeout_secs,
+            proxy,
+        } => {
             let data_type = format_ident!("{data_type}");
+            let method = method.as_ref().map(|method| {
+                let method = gen_http_method(method);
+                quote! { .with_method(#method) }
+            });
+            let headers = headers
+                .iter()
+                .map(|(name, value)| quote! { .with_header(#name, #value) });
+            let query = query.iter().map(|(name, value)| {
+                let value = value.to_string();
+                quote! { .with_query(#name, #value) }
+            });
+            let body = body.as_ref().map(|body| {
+                let body = serde_json::to_string(body).expect("Invalid API reader body");
+                quote! { .with_json_body(serde_json::from_str(#body)?) }
+            });
+            let auth = auth.as_deref().map(|auth| {
+                let auth = gen_http_auth(auth);
+                quote! { .with_auth(#auth) }
+            });
+            let records_path = records_path
+                .as_ref()
+                .map(|path| quote! { .with_records_path(#path) });
+            let split_records = split_records.then(|| quote! { .with_split_records() });
+            let pagination = pagination.as_deref().map(gen_pagination);
+            let watermark = watermark.as_deref().map(gen_watermark);
+            let change_detection = change_detection.as_ref().map(gen_change_detection);
+            let rate_limit = rate_limit.as_ref().map(gen_rate_limit);
+            let max_retries = max_retries.map(|retries| quote! { .with_max_retries(#retries) });
+            let format = format.as_ref().map(|format| {
+                let format = gen_format(format);
+                quote! { .with_format(#format) }
+            });
+            let envelope = envelope.as_ref().map(gen_envelope);
+            let connect_timeout = connect_timeout_secs
+                .map(|secs| quote! { .with_connect_timeout(Duration::from_secs(#secs)) });
+            let read_timeout = read_timeout_secs
+                .map(|secs| quote! { .with_read_timeout(Duration::from_secs(#secs)) });
+            let timeout =
+                timeout_secs.map(|secs| quote! { .with_timeout(Duration::from_secs(#secs)) });
+            let proxy = proxy.as_ref().map(|proxy| quote! { .with_proxy(#proxy) });
+
             quote! {
-                ApiReader::new(#url).with_type::<#data_type>()
+                ApiReader::builder(#url)
+                    #method
+                    #(#headers)*
+                    #(#query)*
+                    #body
+                    #auth
+                    #records_path
+                    #split_records
+                    #pagination
+                    #watermark
+                    #change_detection
+                    #rate_limit
+                    #max_retries
+                    #format
+                    #envelope
+                    #connect_timeout
+                    #read_timeout
+                    #timeout
+                    #proxy
+                    .build()?
+                    .with_type::<#data_type>()
+            }
+        }
+        ReaderConfig::HttpServerReader {
+            address,
+            data_type,
+            paths,
+            verification,
+            buffer,
+            max_body_size,
+        } => {
+            let data_type = format_ident!("{data_type}");
+            let verification = verification.as_deref().map(|verification| {
+                let verification = gen_verification(verification);
+                quote! { .with_verification(#verification) }
+            });
+            let buffer = buffer.map(|buffer| quote! { .with_buffer(#buffer) });
+            let max_body_size = max_body_size.map(|size| quote! { .with_max_body_size(#size) });
+
+            quote! {
+                courier::readers::http_server::HttpServerReader::<#data_type>::builder(#address)
+                    #(.with_path(#paths))*
+                    #verification
+                    #buffer
+                    #max_body_size
+                    .build()?
+            }
+        }
+        ReaderConfig::FileReader {
+            path,
+            data_type,
+            format,
+            follow,
+            start_at_end,
+            poll_interval_ms,
+        } => {
+            let data_type = format_ident!("{data_type}");
+            let format = format.as_ref().map(|format| {
+                let format = gen_format(format);
+                quote! { .with_format(#format) }
+            });
+            let follow = follow.then(|| quote! { .with_follow() });
+            let start_at_end = start_at_end.then(|| quote! { .with_start_at_end() });
+            let poll_interval = poll_interval_ms
+                .map(|ms| quote! { .with_poll_interval(Duration::from_millis(#ms)) });
+
+            quote! {
+                courier::readers::file::FileReader::<#data_type>::builder(#path)
+                    #format
+                    #follow
+                    #start_at_end
+                    #poll_interval
+                    .build()?
+            }
+        }
+        ReaderConfig::DirectoryReader {
+            path,
+            data_type,
+            pattern,
+            format,
+            processed_dir,
+            failed_dir,
+            state_file,
+            poll_interval_ms,
+            settle_time_ms,
+        } => {
+            let data_type = format_ident!("{data_type}");
+            let pattern = pattern
+                .as_ref()
+                .map(|pattern| quote! { .with_pattern(#pattern) });
+            let format = format.as_ref().map(|format| {
+                let format = gen_format(format);
+                quote! { .with_format(#format) }
+            });
+            let processed_dir = processed_dir
+                .as_ref()
+                .map(|dir| quote! { .with_processed_dir(#dir) });
+            let failed_dir = failed_dir
+                .as_ref()
+                .map(|dir| quote! { .with_failed_dir(#dir) });
+            let state_file = state_file
+                .as_ref()
+                .map(|path| quote! { .with_state_file(#path) });
+            let poll_interval = poll_interval_ms
+                .map(|ms| quote! { .with_poll_interval(Duration::from_millis(#ms)) });
+            let settle_time =
+                settle_time_ms.map(|ms| quote! { .with_settle_time(Duration::from_millis(#ms)) });
+
+            quote! {
+                courier::readers::directory::DirectoryReader::<#data_type>::builder(#path)
+                    #pattern
+                    #format
+                    #processed_dir
+                    #failed_dir
+                    #state_file
+                    #poll_interval
+                    #settle_time
+                    .build()?
+            }
+        }
+        ReaderConfig::StdinReader { data_type } => {
+            let data_type = format_ident!("{data_type}");
+            quote! { courier::readers::stdin::StdinReader::<#data_type>::new() }
+        }
+        ReaderConfig::SqlReader {
+            url,
+            query,
+            data_type,
+            params,
+            watermark,
+            connect_timeout_secs,
+        } => {
+            let data_type = format_ident!("{data_type}");
+            let url = gen_secret(url);
+            let params = params.iter().map(|param| {
+                let param = serde_json::to_string(param).expect("Invalid query parameter");
+                quote! { .with_param(serde_json::from_str::<Value>(#param)?) }
+            });
+            let watermark = watermark.as_deref().map(gen_watermark);
+            let connect_timeout = connect_timeout_secs
+                .map(|secs| quote! { .with_connect_timeout(Duration::from_secs(#secs)) });
+
+            quote! {
+                courier::readers::sql::SqlReader::<#data_type>::builder(#url, #query)
+                    #(#params)*
+                    #watermark
+                    #connect_timeout
+                    .build()?
+            }
+        }
+        ReaderConfig::MqttReader {
+            broker,
+            client_id,
+            topics,
+            data_type,
+            qos,
+            username,
+            password,
+            keep_alive_secs,
+            persistent_session,
+            buffer,
+        } => {
+            let data_type = format_ident!("{data_type}");
+            let qos = qos.map(|qos| {
+                let qos = gen_mqtt_qos(qos);
+                quote! { .with_qos(#qos) }
+            });
+            let settings =
+                gen_mqtt_settings(username, password, keep_alive_secs, *persistent_session);
+            let buffer = buffer.map(|buffer| quote! { .with_buffer(#buffer) });
+
+            quote! {
+                courier::readers::mqtt::MqttReader::<#data_type>::builder(
+                    #broker,
+                    #client_id,
+                    vec![#(#topics),*]
+                )
+                #qos
+                #settings
+                #buffer
+                .build()?
+            }
+        }
+        ReaderConfig::AmqpReader {
+            url,
+            queue,
+            data_type,
+            prefetch,
+        } => {
+            let data_type = format_ident!("{data_type}");
+            let url = gen_secret(url);
+            let prefetch = prefetch.map(|prefetch| quote! { .with_prefetch(#prefetch) });
+
+            quote! {
+                courier::readers::amqp::AmqpReader::<#data_type>::builder(#url, #queue)
+                    #prefetch
+                    .build()?
+            }
+        }
+        ReaderConfig::NatsReader {
+            url,
+            subject,
+            data_type,
+            credentials_file,
+            queue_group,
+            jetstream,
+        } => {
+            let data_type = format_ident!("{data_type}");
+            let url = gen_secret(url);
+            let credentials_file = credentials_file
+                .as_ref()
+                .map(|path| quote! { .with_credentials_file(#path) });
+            let queue_group = queue_group
+                .as_ref()
+                .map(|group| quote! { .with_queue_group(#group) });
+            let jetstream = jetstream
+                .as_ref()
+                .map(|NatsJetStreamConfig { stream, durable }| {
+                    quote! { .with_jetstream(#stream, #durable) }
+                });
+
+            quote! {
+                courier::readers::nats::NatsReader::<#data_type>::builder(#url, #subject)
+                    #credentials_file
+                    #queue_group
+                    #jetstream
+                    .build()?
+            }
+        }
+        ReaderConfig::RedisStreamReader {
+            url,
+            stream,
+            group,
+            consumer,
+            data_type,
+            start_id,
+            batch_size,
+            field,
+        } => {
+            let data_type = format_ident!("{data_type}");
+            let url = gen_secret(url);
+            let start_id = start_id.as_ref().map(|id| quote! { .with_start_id(#id) });
+            let batch_size = batch_size.map(|size| quote! { .with_batch_size(#size) });
+            let field = field.as_ref().map(|field| quote! { .with_field(#field) });
+
+            quote! {
+                courier::readers::redis::RedisStreamReader::<#data_type>::builder(
+                    #url,
+                    #stream,
+                    #group,
+                    #consumer
+                )
+                #start_id
+                #batch_size
+                #field
+                .build()?
+            }
+        }
+        ReaderConfig::RedisListReader {
+            url,
+            keys,
+            data_type,
+        } => {
+            let data_type = format_ident!("{data_type}");
+            let url = gen_secret(url);
+
+            quote! {
+                courier::readers::redis::RedisListReader::<#data_type>::builder(
+                    #url,
+                    vec![#(#keys),*]
+                )
+                .build()?
+            }
+        }
+        ReaderConfig::RedisPubSubReader {
+            url,
+            channels,
+            patterns,
+            data_type,
+        } => {
+            let data_type = format_ident!("{data_type}");
+            let url = gen_secret(url);
+
+            quote! {
+                courier::readers::redis::RedisPubSubReader::<#data_type>::builder(
+                    #url,
+                    vec![#(#channels),*]
+                )
+                #(.with_pattern(#patterns))*
+                .build()?
             }
         }
         ReaderConfig::KafkaReader {
@@ -110,14 +577,46 @@ fn gen_reader_expr(reader: &ReaderConfig) -> proc_macro2::TokenStream {
             group_id,
             topics,
             data_type,
+            properties,
+            security,
+            start_position,
+            end_position,
         } => {
             let data_type = format_ident!("{data_type}");
+            let properties = gen_properties(properties);
+            let security = security.as_deref().map(gen_security);
+            let start_position = match start_position {
+                Some(position) => {
+                    quote! { args.start_position.clone().or(Some(#position.parse()?)) }
+                }
+                None => quote! { args.start_position.clone() },
+            };
+            let end_position = match end_position {
+                Some(position) => quote! { args.end_position.clone().or(Some(#position.parse()?)) },
+                None => quote! { args.end_position.clone() },
+            };
+            // Positions given on the command line override the config.
             quote! {
-                KafkaReader::<#data_type>::new(
-                    #brokers,
-                    #group_id,
-                    vec![#(#topics),*]
-                )
+                {
+                    let builder = KafkaReader::<#data_type>::builder(
+                        #brokers,
+                        #group_id,
+                        vec![#(#topics),*]
+                    )
+                    #properties
+                    #security;
+
+                    let builder = match #start_position {
+                        Some(position) => builder.with_start_position(position),
+                        None => builder,
+                    };
+                    let builder = match #end_position {
+                        Some(position) => builder.with_end_position(position),
+                        None => builder,
+                    };
+
+                    builder.build()?
+                }
             }
         }
     }
@@ -129,10 +628,569 @@ fn gen_writer_expr(writer: &WriterConfig) -> proc_macro2::TokenStream {
             brokers,
             topic,
             data_type,
+            properties,
+            security,
+        } => {
+            let data_type = format_ident!("{data_type}");
+            let properties = gen_properties(properties);
+            let security = security.as_deref().map(gen_security);
+            quote! {
+                KafkaWriter::<#data_type>::builder(#brokers, #topic)
+                    #properties
+                    #security
+                    .build()?
+            }
+        }
+        WriterConfig::HttpWriter {
+            url,
+            data_type,
+            method,
+            headers,
+            auth,
+            batch_format,
+            max_retries,
+            connect_timeout_secs,
+            timeout_secs,
+        } => {
+            let data_type = format_ident!("{data_type}");
+            let method = method.as_ref().map(|method| {
+                let method = gen_http_method(method);
+                quote! { .with_method(#method) }
+            });
+            let headers = headers
+                .iter()
+                .map(|(name, value)| quote! { .with_header(#name, #value) });
+            let auth = auth.as_deref().map(|auth| {
+                let auth = gen_http_auth(auth);
+                quote! { .with_auth(#auth) }
+            });
+            let batch_format = batch_format.as_ref().map(|batch_format| {
+                let batch_format = match batch_format {
+                    BatchFormatConfig::Individual => quote! { Individual },
+                    BatchFormatConfig::JsonArray => quote! { JsonArray },
+                    BatchFormatConfig::Ndjson => quote! { Ndjson },
+                };
+                quote! { .with_batch_format(courier::writers::http::BatchFormat::#batch_format) }
+            });
+            let max_retries = max_retries.map(|retries| quote! { .with_max_retries(#retries) });
+            let connect_timeout = connect_timeout_secs
+                .map(|secs| quote! { .with_connect_timeout(Duration::from_secs(#secs)) });
+            let timeout =
+                timeout_secs.map(|secs| quote! { .with_timeout(Duration::from_secs(#secs)) });
+
+            quote! {
+                courier::writers::http::HttpWriter::<#data_type>::builder(#url)
+                    #method
+                    #(#headers)*
+                    #auth
+                    #batch_format
+                    #max_retries
+                    #connect_timeout
+                    #timeout
+                    .build()?
+            }
+        }
+        WriterConfig::FileWriter {
+            path,
+            data_type,
+            format,
+            csv_columns,
+            max_bytes,
+            max_age_secs,
+            gzip,
+        } => {
+            let data_type = format_ident!("{data_type}");
+            let format = format.as_ref().map(|format| {
+                let format = gen_format(format);
+                quote! { .with_format(#format) }
+            });
+            let columns = csv_columns
+                .as_ref()
+                .map(|columns| quote! { .with_columns(vec![#(#columns.into()),*]) });
+            let max_bytes = max_bytes.map(|bytes| quote! { .with_max_bytes(#bytes) });
+            let max_age =
+                max_age_secs.map(|secs| quote! { .with_max_age(Duration::from_secs(#secs)) });
+            let gzip = gzip.then(|| quote! { .with_gzip() });
+
+            quote! {
+                courier::writers::file::FileWriter::<#data_type>::builder(#path)
+                    #format
+                    #columns
+                    #max_bytes
+                    #max_age
+                    #gzip
+                    .build()?
+            }
+        }
+        WriterConfig::StdoutWriter {
+            data_type,
+            pretty,
+            key_prefix,
+        } => {
+            let data_type = format_ident!("{data_type}");
+            let pretty = pretty.then(|| quote! { .with_pretty() });
+            let key_prefix = key_prefix.then(|| quote! { .with_key_prefix() });
+
+            quote! {
+                courier::writers::stdout::StdoutWriter::<#data_type>::builder()
+                    #pretty
+                    #key_prefix
+                    .build()?
+            }
+        }
+        WriterConfig::PostgresWriter {
+            url,
+            table,
+            data_type,
+            columns,
+            upsert_key,
+            max_connections,
+            connect_timeout_secs,
+        } => {
+            let data_type = format_ident!("{data_type}");
+            let url = gen_secret(url);
+            let columns = columns
+                .iter()
+                .map(|(column, field)| quote! { .with_column(#column, #field) });
+            let upsert = (!upsert_key.is_empty())
+                .then(|| quote! { .with_upsert(vec![#(#upsert_key.into()),*]) });
+            let max_connections = max_connections.map(|max| quote! { .with_max_connections(#max) });
+            let connect_timeout = connect_timeout_secs
+                .map(|secs| quote! { .with_connect_timeout(Duration::from_secs(#secs)) });
+
+            quote! {
+                courier::writers::postgres::PostgresWriter::<#data_type>::builder(#url, #table)
+                    #(#columns)*
+                    #upsert
+                    #max_connections
+                    #connect_timeout
+                    .build()?
+            }
+        }
+        WriterConfig::SqliteWriter {
+            path,
+            table,
+            data_type,
+            columns,
+            upsert_key,
+        } => {
+            let data_type = format_ident!("{data_type}");
+            let columns = columns
+                .iter()
+                .map(|(name, sql_type)| quote! { .with_column(#name, #sql_type) });
+            let upsert = (!upsert_key.is_empty())
+                .then(|| quote! { .with_upsert(vec![#(#upsert_key.into()),*]) });
+
+            quote! {
+                courier::writers::sqlite::SqliteWriter::<#data_type>::builder(#path, #table)
+                    #(#columns)*
+                    #upsert
+                    .build()?
+            }
+        }
+        WriterConfig::MqttWriter {
+            broker,
+            client_id,
+            topic,
+            data_type,
+            qos,
+            retain,
+            username,
+            password,
+            keep_alive_secs,
+            persistent_session,
+        } => {
+            let data_type = format_ident!("{data_type}");
+            let qos = qos.map(|qos| {
+                let qos = gen_mqtt_qos(qos);
+                quote! { .with_qos(#qos) }
+            });
+            let retain = retain.then(|| quote! { .with_retain() });
+            let settings =
+                gen_mqtt_settings(username, password, keep_alive_secs, *persistent_session);
+
+            quote! {
+                courier::writers::mqtt::MqttWriter::<#data_type>::builder(
+                    #broker,
+                    #client_id,
+                    #topic
+                )
+                #qos
+                #retain
+                #settings
+                .build()?
+            }
+        }
+        WriterConfig::AmqpWriter {
+            url,
+            exchange,
+            routing_key,
+            data_type,
+            mandatory,
+            persistent,
         } => {
             let data_type = format_ident!("{data_type}");
+            let url = gen_secret(url);
+            let mandatory = mandatory.then(|| quote! { .with_mandatory() });
+            let persistent = persistent.then(|| quote! { .with_persistent() });
+
+            quote! {
+                courier::writers::amqp::AmqpWriter::<#data_type>::builder(
+                    #url,
+                    #exchange,
+                    #routing_key
+                )
+                #mandatory
+                #persistent
+                .build()?
+            }
+        }
+        WriterConfig::NatsWriter {
+            url,
+            subject,
+            data_type,
+            credentials_file,
+            jetstream,
+        } => {
+            let data_type = format_ident!("{data_type}");
+            let url = gen_secret(url);
+            let credentials_file = credentials_file
+                .as_ref()
+                .map(|path| quote! { .with_credentials_file(#path) });
+            let jetstream = jetstream.then(|| quote! { .with_jetstream() });
+
+            quote! {
+                courier::writers::nats::NatsWriter::<#data_type>::builder(#url, #subject)
+                    #credentials_file
+                    #jetstream
+                    .build()?
+            }
+        }
+        WriterConfig::RedisWriter {
+            url,
+            command,
+            key,
+            data_type,
+            field,
+            max_len,
+            ttl_secs,
+        } => {
+            let data_type = format_ident!("{data_type}");
+            let url = gen_secret(url);
+            let command = match command {
+                RedisCommandConfig::Xadd => quote! { Xadd },
+                RedisCommandConfig::Lpush => quote! { Lpush },
+                RedisCommandConfig::Rpush => quote! { Rpush },
+                RedisCommandConfig::Publish => quote! { Publish },
+                RedisCommandConfig::Set => quote! { Set },
+            };
+            let field = field.as_ref().map(|field| quote! { .with_field(#field) });
+            let max_len = max_len.map(|max_len| quote! { .with_max_len(#max_len) });
+            let ttl = ttl_secs.map(|secs| quote! { .with_ttl(Duration::from_secs(#secs)) });
+
+            quote! {
+                courier::writers::redis::RedisWriter::<#data_type>::builder(
+                    #url,
+                    courier::writers::redis::RedisCommand::#command,
+                    #key
+                )
+                #field
+                #max_len
+                #ttl
+                .build()?
+            }
+        }
+    }
+}
+
+fn gen_mqtt_qos(qos: u8) -> proc_macro2::TokenStream {
+    match qos {
+        0 => quote! { courier::mqtt::QoS::AtMostOnce },
+        1 => quote! { courier::mqtt::QoS::AtLeastOnce },
+        2 => quote! { courier::mqtt::QoS::ExactlyOnce },
+        _ => panic!("MQTT qos must be 0, 1 or 2, got {qos}"),
+    }
+}
+
+fn gen_mqtt_settings(
+    username: &Option<String>,
+    password: &Option<SecretConfig>,
+    keep_alive_secs: &Option<u64>,
+    persistent_session: bool,
+) -> proc_macro2::TokenStream {
+    let credentials = match (username, password) {
+        (Some(username), Some(password)) => {
+            let password = gen_secret(password);
+            Some(quote! { .with_credentials(#username, #password) })
+        }
+        (None, None) => None,
+        _ => panic!("MQTT config requires both username and password"),
+    };
+    let keep_alive =
+        keep_alive_secs.map(|secs| quote! { .with_keep_alive(Duration::from_secs(#secs)) });
+    let persistent_session = persistent_session.then(|| quote! { .with_persistent_session() });
+
+    quote! {
+        #credentials
+        #keep_alive
+        #persistent_session
+    }
+}
+
+fn gen_properties(properties: &BTreeMap<String, PropertyValue>) -> proc_macro2::TokenStream {
+    let keys = properties.keys();
+    let values = properties.values().map(ToString::to_string);
+    quote! {
+        #(.with_property(#keys, #values))*
+    }
+}
+
+fn gen_security(security: &KafkaSecurityConfig) -> proc_macro2::TokenStream {
+    let protocol = match security.protocol {
+        SecurityProtocolConfig::Plaintext => quote! { Plaintext },
+        SecurityProtocolConfig::Ssl => quote! { Ssl },
+        SecurityProtocolConfig::SaslPlaintext => quote! { SaslPlaintext },
+        SecurityProtocolConfig::SaslSsl => quote! { SaslSsl },
+    };
+
+    let sasl = security.sasl_mechanism.as_ref().map(|mechanism| {
+        let mechanism = match mechanism {
+            SaslMechanismConfig::Plain => quote! { Plain },
+            SaslMechanismConfig::ScramSha256 => quote! { ScramSha256 },
+            SaslMechanismConfig::ScramSha512 => quote! { ScramSha512 },
+        };
+        let username = gen_secret(
+            security
+                .username
+                .as_ref()
+                .expect("Kafka security config with sasl_mechanism requires a username"),
+        );
+        let password = gen_secret(
+            security
+                .password
+                .as_ref()
+                .expect("Kafka security config with sasl_mechanism requires a password"),
+        );
+        quote! {
+            .with_sasl(
+                courier::schemas::kafka::SaslMechanism::#mechanism,
+                #username,
+                #password
+            )
+        }
+    });
+
+    let ca_location = security
+        .ssl_ca_location
+        .as_ref()
+        .map(|path| quote! { .with_ca_location(#path) });
+
+    let client_certificate = match (
+        &security.ssl_certificate_location,
+        &security.ssl_key_location,
+    ) {
+        (Some(certificate), Some(key)) => {
+            Some(quote! { .with_client_certificate(#certificate, #key) })
+        }
+        (None, None) => None,
+        _ => panic!(
+            "Kafka security config requires both ssl_certificate_location and ssl_key_location"
+        ),
+    };
+
+    let key_password = security.ssl_key_password.as_ref().map(|password| {
+        let password = gen_secret(password);
+        quote! { .with_key_password(#password) }
+    });
+
+    quote! {
+        .with_security(
+            courier::schemas::kafka::KafkaSecurity::new(
+                courier::schemas::kafka::SecurityProtocol::#protocol
+            )
+            #sasl
+            #ca_location
+            #client_certificate
+            #key_password
+        )
+    }
+}
+
+fn gen_secret(secret: &SecretConfig) -> proc_macro2::TokenStream {
+    match secret {
+        SecretConfig::Value(value) => quote! { courier::secret::Secret::value(#value) },
+        SecretConfig::Env { env } => quote! { courier::secret::Secret::env(#env) },
+        SecretConfig::File { file } => quote! { courier::secret::Secret::file(#file) },
+    }
+}
+
+fn gen_http_method(method: &HttpMethodConfig) -> proc_macro2::TokenStream {
+    let method = match method {
+        HttpMethodConfig::Get => quote! { GET },
+        HttpMethodConfig::Post => quote! { POST },
+        HttpMethodConfig::Put => quote! { PUT },
+        HttpMethodConfig::Patch => quote! { PATCH },
+        HttpMethodConfig::Delete => quote! { DELETE },
+    };
+    quote! { courier::readers::api::Method::#method }
+}
+
+fn gen_http_auth(auth: &HttpAuthConfig) -> proc_macro2::TokenStream {
+    match auth {
+        HttpAuthConfig::Bearer { token } => {
+            let token = gen_secret(token);
+            quote! { courier::auth::HttpAuth::bearer(#token) }
+        }
+        HttpAuthConfig::Basic { username, password } => {
+            let password = gen_secret(password);
+            quote! { courier::auth::HttpAuth::basic(#username, #password) }
+        }
+        HttpAuthConfig::ApiKey {
+            name,
+            value,
+            location,
+        } => {
+            let value = gen_secret(value);
+            let location = match location {
+                ApiKeyLocationConfig::Header => quote! { Header },
+                ApiKeyLocationConfig::Query => quote! { Query },
+            };
+            quote! {
+                courier::auth::HttpAuth::api_key(
+                    #name,
+                    #value,
+                    courier::auth::ApiKeyLocation::#location
+                )
+            }
+        }
+        HttpAuthConfig::OAuth2 {
+            token_url,
+            client_id,
+            client_secret,
+            scopes,
+        } => {
+            let client_secret = gen_secret(client_secret);
+            quote! {
+                courier::auth::HttpAuth::oauth2(#token_url, #client_id, #client_secret)
+                    .with_scopes(vec![#(#scopes),*])
+            }
+        }
+    }
+}
+
+fn gen_pagination(pagination: &PaginationConfig) -> proc_macro2::TokenStream {
+    let strategy = match &pagination.strategy {
+        PaginationStrategyConfig::PageNumber { param, start } => quote! {
+            courier::readers::api::Pagination::PageNumber { param: #param.into(), start: #start }
+        },
+        PaginationStrategyConfig::Offset { param, start } => quote! {
+            courier::readers::api::Pagination::Offset { param: #param.into(), start: #start }
+        },
+        PaginationStrategyConfig::Cursor { cursor_path, param } => quote! {
+            courier::readers::api::Pagination::Cursor {
+                cursor_path: #cursor_path.into(),
+                param: #param.into(),
+            }
+        },
+        PaginationStrategyConfig::LinkHeader => quote! {
+            courier::readers::api::Pagination::LinkHeader
+        },
+        PaginationStrategyConfig::NextUrl { path } => quote! {
+            courier::readers::api::Pagination::NextUrl { path: #path.into() }
+        },
+    };
+    let max_pages = pagination
+        .max_pages
+        .map(|max_pages| quote! { .with_max_pages(#max_pages) });
+
+    quote! { .with_pagination(#strategy) #max_pages }
+}
+
+fn gen_watermark(watermark: &WatermarkConfig) -> proc_macro2::TokenStream {
+    let field = &watermark.field;
+    let param = watermark
+        .param
+        .as_ref()
+        .map(|param| quote! { .with_param(#param) });
+    let initial = watermark.initial.as_ref().map(|initial| {
+        let initial = serde_json::to_string(initial).expect("Invalid watermark initial value");
+        quote! { .with_initial(serde_json::from_str::<Value>(#initial)?) }
+    });
+    let state_file = watermark
+        .state_file
+        .as_ref()
+        .map(|path| quote! { .with_state_file(#path) });
+
+    quote! {
+        .with_watermark(
+            courier::watermark::Watermark::new(#field)
+                #param
+                #initial
+                #state_file
+        )
+    }
+}
+
+fn gen_change_detection(change_detection: &ChangeDetectionConfig) -> proc_macro2::TokenStream {
+    let fields = &change_detection.fields;
+    quote! {
+        .with_change_detection(
+            courier::readers::api::ChangeDetection::new()
+                .with_fields(vec![#(#fields.into()),*])
+        )
+    }
+}
+
+fn gen_rate_limit(rate_limit: &RateLimitConfig) -> proc_macro2::TokenStream {
+    let RateLimitConfig {
+        requests_per_sec,
+        burst,
+        shared,
+    } = rate_limit;
+    if *shared {
+        quote! { .with_shared_rate_limit(#requests_per_sec, #burst) }
+    } else {
+        quote! { .with_rate_limit(#requests_per_sec, #burst) }
+    }
+}
+
+fn gen_format(format: &FormatConfig) -> proc_macro2::TokenStream {
+    let format = match format {
+        FormatConfig::Json => quote! { Json },
+        FormatConfig::Text => quote! { Text },
+        FormatConfig::Csv => quote! { Csv },
+        FormatConfig::Ndjson => quote! { Ndjson },
+        FormatConfig::Xml => quote! { Xml },
+    };
+    quote! { courier::format::Format::#format }
+}
+
+fn gen_envelope(envelope: &EnvelopeConfig) -> proc_macro2::TokenStream {
+    let headers = &envelope.headers;
+    quote! {
+        .with_envelope(
+            courier::readers::api::Envelope::new()
+                .with_headers(vec![#(#headers.into()),*])
+        )
+    }
+}
+
+fn gen_verification(verification: &VerificationConfig) -> proc_macro2::TokenStream {
+    match verification {
+        VerificationConfig::SharedSecret { header, secret } => {
+            let secret = gen_secret(secret);
+            quote! {
+                courier::readers::http_server::Verification::shared_secret(#header, #secret)
+            }
+        }
+        VerificationConfig::HmacSha256 {
+            header,
+            secret,
+            prefix,
+        } => {
+            let secret = gen_secret(secret);
             quote! {
-                KafkaWriter::<#data_type>::new(#brokers, #topic)
+                courier::readers::http_server::Verification::hmac_sha256(#header, #secret, #prefix)
             }
         }
     }
@@ -1,3 +1,6 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
 use serde::Deserialize;
 
 #[derive(Debug, Deserialize)]
@@ -14,6 +17,8 @@ pub enum OperationConfig {
         reader: ReaderConfig,
         writer: WriterConfig,
         interval_secs: u64,
+        #[serde(default)]
+        write_mode: WriteModeConfig,
     },
     #[serde(rename = "Stream")]
     Stream {
@@ -27,9 +32,32 @@ pub enum OperationConfig {
         reader: ReaderConfig,
         writers: Vec<WriterConfig>,
         interval_secs: u64,
+        #[serde(default)]
+        write_mode: WriteModeConfig,
     },
 }
 
+impl OperationConfig {
+    pub fn name(&self) -> &str {
+        match self {
+            OperationConfig::Interval { name, .. }
+            | OperationConfig::Stream { name, .. }
+            | OperationConfig::IntervalFanout { name, .. } => name,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub enum WriteModeConfig {
+    #[default]
+    #[serde(rename = "individual")]
+    Individual,
+    #[serde(rename = "batch")]
+    Batch,
+}
+
+// Variants are named after the reader they build.
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug, Deserialize)]
 #[serde(tag = "type")]
 pub enum ReaderConfig {
@@ -39,11 +67,143 @@ pub enum ReaderConfig {
         group_id: String,
         topics: Vec<String>,
         data_type: String,
+        #[serde(default)]
+        properties: BTreeMap<String, PropertyValue>,
+        security: Option<Box<KafkaSecurityConfig>>,
+        start_position: Option<String>,
+        end_position: Option<String>,
     },
     #[serde(rename = "api")]
-    ApiReader { url: String, data_type: String },
+    ApiReader {
+        url: String,
+        data_type: String,
+        method: Option<HttpMethodConfig>,
+        #[serde(default)]
+        headers: BTreeMap<String, String>,
+        #[serde(default)]
+        query: BTreeMap<String, PropertyValue>,
+        body: Option<toml::Value>,
+        auth: Option<Box<HttpAuthConfig>>,
+        records_path: Option<String>,
+        #[serde(default)]
+        split_records: bool,
+        pagination: Option<Box<PaginationConfig>>,
+        watermark: Option<Box<WatermarkConfig>>,
+        change_detection: Option<ChangeDetectionConfig>,
+        rate_limit: Option<RateLimitConfig>,
+        max_retries: Option<u32>,
+        format: Option<FormatConfig>,
+        envelope: Option<EnvelopeConfig>,
+        connect_timeout_secs: Option<u64>,
+        read_timeout_secs: Option<u64>,
+        timeout_secs: Option<u64>,
+        proxy: Option<String>,
+    },
+    #[serde(rename = "http_server")]
+    HttpServerReader {
+        address: String,
+        data_type: String,
+        #[serde(default)]
+        paths: Vec<String>,
+        verification: Option<Box<VerificationConfig>>,
+        buffer: Option<usize>,
+        max_body_size: Option<usize>,
+    },
+    #[serde(rename = "file")]
+    FileReader {
+        path: String,
+        data_type: String,
+        format: Option<FormatConfig>,
+        #[serde(default)]
+        follow: bool,
+        #[serde(default)]
+        start_at_end: bool,
+        poll_interval_ms: Option<u64>,
+    },
+    #[serde(rename = "directory")]
+    DirectoryReader {
+        path: String,
+        data_type: String,
+        pattern: Option<String>,
+        format: Option<FormatConfig>,
+        processed_dir: Option<String>,
+        failed_dir: Option<String>,
+        state_file: Option<String>,
+        poll_interval_ms: Option<u64>,
+        settle_time_ms: Option<u64>,
+    },
+    #[serde(rename = "stdin")]
+    StdinReader { data_type: String },
+    #[serde(rename = "sql")]
+    SqlReader {
+        url: SecretConfig,
+        query: String,
+        data_type: String,
+        #[serde(default)]
+        params: Vec<toml::Value>,
+        watermark: Option<Box<WatermarkConfig>>,
+        connect_timeout_secs: Option<u64>,
+    },
+    #[serde(rename = "mqtt")]
+    MqttReader {
+        broker: String,
+        client_id: String,
+        topics: Vec<String>,
+        data_type: String,
+        qos: Option<u8>,
+        username: Option<String>,
+        password: Option<SecretConfig>,
+        keep_alive_secs: Option<u64>,
+        #[serde(default)]
+        persistent_session: bool,
+        buffer: Option<usize>,
+    },
+    #[serde(rename = "amqp")]
+    AmqpReader {
+        url: SecretConfig,
+        queue: String,
+        data_type: String,
+        prefetch: Option<u16>,
+    },
+    #[serde(rename = "nats")]
+    NatsReader {
+        url: SecretConfig,
+        subject: String,
+        data_type: String,
+        credentials_file: Option<String>,
+        queue_group: Option<String>,
+        jetstream: Option<NatsJetStreamConfig>,
+    },
+    #[serde(rename = "redis_stream")]
+    RedisStreamReader {
+        url: SecretConfig,
+        stream: String,
+        group: String,
+        consumer: String,
+        data_type: String,
+        start_id: Option<String>,
+        batch_size: Option<usize>,
+        field: Option<String>,
+    },
+    #[serde(rename = "redis_list")]
+    RedisListReader {
+        url: SecretConfig,
+        keys: Vec<String>,
+        data_type: String,
+    },
+    #[serde(rename = "redis_pubsub")]
+    RedisPubSubReader {
+        url: SecretConfig,
+        #[serde(default)]
+        channels: Vec<String>,
+        #[serde(default)]
+        patterns: Vec<String>,
+        data_type: String,
+    },
 }
 
+// Variants are named after the writer they build.
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug, Deserialize)]
 #[serde(tag = "type")]
 pub enum WriterConfig {
@@ -52,5 +212,361 @@ pub enum WriterConfig {
         brokers: String,
         topic: String,
         data_type: String,
+        #[serde(default)]
+        properties: BTreeMap<String, PropertyValue>,
+        security: Option<Box<KafkaSecurityConfig>>,
+    },
+    #[serde(rename = "http")]
+    HttpWriter {
+        url: String,
+        data_type: String,
+        method: Option<HttpMethodConfig>,
+        #[serde(default)]
+        headers: BTreeMap<String, String>,
+        auth: Option<Box<HttpAuthConfig>>,
+        batch_format: Option<BatchFormatConfig>,
+        max_retries: Option<u32>,
+        connect_timeout_secs: Option<u64>,
+        timeout_secs: Option<u64>,
+    },
+    #[serde(rename = "file")]
+    FileWriter {
+        path: String,
+        data_type: String,
+        format: Option<FormatConfig>,
+        csv_columns: Option<Vec<String>>,
+        max_bytes: Option<u64>,
+        max_age_secs: Option<u64>,
+        #[serde(default)]
+        gzip: bool,
+    },
+    #[serde(rename = "stdout")]
+    StdoutWriter {
+        data_type: String,
+        #[serde(default)]
+        pretty: bool,
+        #[serde(default)]
+        key_prefix: bool,
+    },
+    #[serde(rename = "postgres")]
+    PostgresWriter {
+        url: SecretConfig,
+        table: String,
+        data_type: String,
+        /// Column names mapped to dotted field paths.
+        #[serde(default)]
+        columns: BTreeMap<String, String>,
+        #[serde(default)]
+        upsert_key: Vec<String>,
+        max_connections: Option<u32>,
+        connect_timeout_secs: Option<u64>,
+    },
+    #[serde(rename = "sqlite")]
+    SqliteWriter {
+        path: String,
+        table: String,
+        data_type: String,
+        /// Column names mapped to SQL types, used to create the table.
+        #[serde(default)]
+        columns: BTreeMap<String, String>,
+        #[serde(default)]
+        upsert_key: Vec<String>,
+    },
+    #[serde(rename = "mqtt")]
+    MqttWriter {
+        broker: String,
+        client_id: String,
+        /// Topic, rendered for each record.
+        topic: String,
+        data_type: String,
+        qos: Option<u8>,
+        #[serde(default)]
+        retain: bool,
+        username: Option<String>,
+        password: Option<SecretConfig>,
+        keep_alive_secs: Option<u64>,
+        #[serde(default)]
+        persistent_session: bool,
+    },
+    #[serde(rename = "amqp")]
+    AmqpWriter {
+        url: SecretConfig,
+        /// Empty for the default exchange.
+        #[serde(default)]
+        exchange: String,
+        /// Routing key, rendered for each record.
+        routing_key: String,
+        data_type: String,
+        #[serde(default)]
+        mandatory: bool,
+        #[serde(default)]
+        persistent: bool,
+    },
+    #[serde(rename = "nats")]
+    NatsWriter {
+        url: SecretConfig,
+        /// Subject, rendered for each record.
+        subject: String,
+        data_type: String,
+        credentials_file: Option<String>,
+        #[serde(default)]
+        jetstream: bool,
+    },
+    #[serde(rename = "redis")]
+    RedisWriter {
+        url: SecretConfig,
+        command: RedisCommandConfig,
+        /// Stream, list, channel or key name, rendered for each record.
+        key: String,
+        data_type: String,
+        field: Option<String>,
+        max_len: Option<usize>,
+        ttl_secs: Option<u64>,
+    },
+}
+
+/// A librdkafka property value. librdkafka only takes strings, but numbers and
+/// booleans are accepted too so that e.g. `linger.ms = 5` can be written.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum PropertyValue {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+}
+
+impl fmt::Display for PropertyValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PropertyValue::String(v) => write!(f, "{v}"),
+            PropertyValue::Integer(v) => write!(f, "{v}"),
+            PropertyValue::Float(v) => write!(f, "{v}"),
+            PropertyValue::Boolean(v) => write!(f, "{v}"),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct KafkaSecurityConfig {
+    pub protocol: SecurityProtocolConfig,
+    pub sasl_mechanism: Option<SaslMechanismConfig>,
+    pub username: Option<SecretConfig>,
+    pub password: Option<SecretConfig>,
+    pub ssl_ca_location: Option<String>,
+    pub ssl_certificate_location: Option<String>,
+    pub ssl_key_location: Option<String>,
+    pub ssl_key_password: Option<SecretConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+pub enum SecurityProtocolConfig {
+    #[serde(rename = "PLAINTEXT")]
+    Plaintext,
+    #[serde(rename = "SSL")]
+    Ssl,
+    #[serde(rename = "SASL_PLAINTEXT")]
+    SaslPlaintext,
+    #[serde(rename = "SASL_SSL")]
+    SaslSsl,
+}
+
+#[derive(Debug, Deserialize)]
+pub enum SaslMechanismConfig {
+    #[serde(rename = "PLAIN")]
+    Plain,
+    #[serde(rename = "SCRAM-SHA-256")]
+    ScramSha256,
+    #[serde(rename = "SCRAM-SHA-512")]
+    ScramSha512,
+}
+
+/// A secret given inline (`"..."`), read from the environment
+/// (`{ env = "NAME" }`) or read from a file (`{ file = "/path" }`).
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum SecretConfig {
+    Value(String),
+    Env { env: String },
+    File { file: String },
+}
+
+#[derive(Debug, Deserialize)]
+pub enum HttpMethodConfig {
+    #[serde(rename = "GET")]
+    Get,
+    #[serde(rename = "POST")]
+    Post,
+    #[serde(rename = "PUT")]
+    Put,
+    #[serde(rename = "PATCH")]
+    Patch,
+    #[serde(rename = "DELETE")]
+    Delete,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type")]
+pub enum HttpAuthConfig {
+    #[serde(rename = "bearer")]
+    Bearer { token: SecretConfig },
+    #[serde(rename = "basic")]
+    Basic {
+        username: String,
+        password: SecretConfig,
+    },
+    #[serde(rename = "api_key")]
+    ApiKey {
+        name: String,
+        value: SecretConfig,
+        #[serde(default)]
+        location: ApiKeyLocationConfig,
+    },
+    #[serde(rename = "oauth2")]
+    OAuth2 {
+        token_url: String,
+        client_id: String,
+        client_secret: SecretConfig,
+        #[serde(default)]
+        scopes: Vec<String>,
+    },
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub enum ApiKeyLocationConfig {
+    #[default]
+    #[serde(rename = "header")]
+    Header,
+    #[serde(rename = "query")]
+    Query,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PaginationConfig {
+    #[serde(flatten)]
+    pub strategy: PaginationStrategyConfig,
+    pub max_pages: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type")]
+pub enum PaginationStrategyConfig {
+    #[serde(rename = "page_number")]
+    PageNumber {
+        param: String,
+        #[serde(default = "default_first_page")]
+        start: u64,
+    },
+    #[serde(rename = "offset")]
+    Offset {
+        param: String,
+        #[serde(default)]
+        start: u64,
+    },
+    #[serde(rename = "cursor")]
+    Cursor { cursor_path: String, param: String },
+    #[serde(rename = "link_header")]
+    LinkHeader,
+    #[serde(rename = "next_url")]
+    NextUrl { path: String },
+}
+
+fn default_first_page() -> u64 {
+    1
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WatermarkConfig {
+    pub field: String,
+    pub param: Option<String>,
+    pub initial: Option<toml::Value>,
+    pub state_file: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangeDetectionConfig {
+    #[serde(default)]
+    pub fields: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RateLimitConfig {
+    pub requests_per_sec: f64,
+    #[serde(default = "default_burst")]
+    pub burst: u32,
+    /// Shares the limit with every reader calling the same host.
+    #[serde(default)]
+    pub shared: bool,
+}
+
+fn default_burst() -> u32 {
+    1
+}
+
+#[derive(Debug, Deserialize)]
+pub enum FormatConfig {
+    #[serde(rename = "json")]
+    Json,
+    #[serde(rename = "text")]
+    Text,
+    #[serde(rename = "csv")]
+    Csv,
+    #[serde(rename = "ndjson")]
+    Ndjson,
+    #[serde(rename = "xml")]
+    Xml,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EnvelopeConfig {
+    #[serde(default)]
+    pub headers: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub enum BatchFormatConfig {
+    #[serde(rename = "individual")]
+    Individual,
+    #[serde(rename = "json_array")]
+    JsonArray,
+    #[serde(rename = "ndjson")]
+    Ndjson,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NatsJetStreamConfig {
+    pub stream: String,
+    /// Durable consumer name.
+    pub durable: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub enum RedisCommandConfig {
+    #[serde(rename = "xadd")]
+    Xadd,
+    #[serde(rename = "lpush")]
+    Lpush,
+    #[serde(rename = "rpush")]
+    Rpush,
+    #[serde(rename = "publish")]
+    Publish,
+    #[serde(rename = "set")]
+    Set,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type")]
+pub enum VerificationConfig {
+    #[serde(rename = "shared_secret")]
+    SharedSecret {
+        header: String,
+        secret: SecretConfig,
+    },
+    #[serde(rename = "hmac_sha256")]
+    HmacSha256 {
+        header: String,
+        secret: SecretConfig,
+        #[serde(default)]
+        prefix: String,
     },
 }
@@ -1,3 +1,4 @@
+use courier::cli::Args;
 use courier::operations::*;
 use courier::readers::api::ApiReader;
 use courier::readers::kafka::KafkaReader;
@@ -5,29 +6,124 @@ use courier::writers::kafka::KafkaWriter;
 use courier::Courier;
 use serde_json::Value;
 use std::time::Duration;
-pub fn courier_from_config() -> Courier {
-    let mut operations: Vec<Box<dyn Operation>> = Vec::new();
-    {
-        let reader =
-            KafkaReader::<Value>::new("localhost:9092", "user-events-consumer", vec!["topic1"]);
-        let writer = KafkaWriter::<Value>::new("localhost:9092", "topic2");
-        let operation = StreamOperation::new("kafka->kafka", reader, writer);
-        operations.push(Box::new(operation));
+type OperationBuilder = fn(&Args, &mut Vec<String>) -> Option<Box<dyn Operation>>;
+#[doc = r" Builds one reader or writer, recording its error instead of"]
+#[doc = r" stopping at it."]
+fn build_component<T>(
+    component: &str,
+    errors: &mut Vec<String>,
+    build: impl FnOnce() -> anyhow::Result<T>,
+) -> Option<T> {
+    match build() {
+        Ok(built) => Some(built),
+        Err(e) => {
+            errors.push(format!("{component}: {e:#}"));
+            None
+        }
     }
-    {
-        let reader = ApiReader::new("http://192.168.47.204:8000").with_type::<Value>();
-        let writer = KafkaWriter::<Value>::new("localhost:9092", "topic1");
-        let operation =
-            IntervalOperation::new("apiitalo->kafka", reader, writer, Duration::from_secs(3u64));
-        operations.push(Box::new(operation));
+}
+#[allow(unused_variables, clippy::needless_question_mark)]
+fn build_operation_0(args: &Args, errors: &mut Vec<String>) -> Option<Box<dyn Operation>> {
+    let reader = build_component("reader", errors, || {
+        Ok({
+            let builder = KafkaReader::<Value>::builder(
+                "localhost:9092",
+                "user-events-consumer",
+                vec!["topic1"],
+            )
+            .with_property("auto.offset.reset", "earliest");
+            let builder = match args.start_position.clone() {
+                Some(position) => builder.with_start_position(position),
+                None => builder,
+            };
+            let builder = match args.end_position.clone() {
+                Some(position) => builder.with_end_position(position),
+                None => builder,
+            };
+            builder.build()?
+        })
+    });
+    let writer = build_component("writer", errors, || {
+        Ok(KafkaWriter::<Value>::builder("localhost:9092", "topic2")
+            .with_property("acks", "all")
+            .with_property("linger.ms", "5")
+            .build()?)
+    });
+    let (Some(reader), Some(writer)) = (reader, writer) else {
+        return None;
+    };
+    let operation = StreamOperation::new("kafka->kafka", reader, writer);
+    Some(Box::new(operation))
+}
+#[allow(unused_variables, clippy::needless_question_mark)]
+fn build_operation_1(args: &Args, errors: &mut Vec<String>) -> Option<Box<dyn Operation>> {
+    let reader = build_component("reader", errors, || {
+        Ok(ApiReader::builder("http://192.168.47.204:8000")
+            .with_header("Accept", "application/json")
+            .with_timeout(Duration::from_secs(10u64))
+            .build()?
+            .with_type::<Value>())
+    });
+    let writer = build_component("writer", errors, || {
+        Ok(KafkaWriter::<Value>::builder("localhost:9092", "topic1").build()?)
+    });
+    let (Some(reader), Some(writer)) = (reader, writer) else {
+        return None;
+    };
+    let operation =
+        IntervalOperation::new("apiitalo->kafka", reader, writer, Duration::from_secs(3u64))
+            .with_write_mode(WriteMode::Individual);
+    Some(Box::new(operation))
+}
+#[allow(unused_variables, clippy::needless_question_mark)]
+fn build_operation_2(args: &Args, errors: &mut Vec<String>) -> Option<Box<dyn Operation>> {
+    let reader = build_component("reader", errors, || {
+        Ok(ApiReader::builder("http://192.168.47.204:8000")
+            .build()?
+            .with_type::<Value>())
+    });
+    let writer_0 = build_component("writer 1", errors, || {
+        Ok(KafkaWriter::<Value>::builder("localhost:9092", "topic3").build()?)
+    });
+    let writer_1 = build_component("writer 2", errors, || {
+        Ok(KafkaWriter::<Value>::builder("localhost:9092", "topic4").build()?)
+    });
+    let (Some(reader), Some(writer_0), Some(writer_1)) = (reader, writer_0, writer_1) else {
+        return None;
+    };
+    let mut operation =
+        IntervalFanoutOperation::new("api->multi-kakfa", reader, Duration::from_secs(5u64))
+            .with_write_mode(WriteMode::Individual);
+    operation.add_writer(writer_0);
+    operation.add_writer(writer_1);
+    Some(Box::new(operation))
+}
+pub fn courier_from_config(args: &Args) -> anyhow::Result<Courier> {
+    let builders: Vec<(&str, OperationBuilder)> = vec![
+        ("kafka->kafka", build_operation_0),
+        ("apiitalo->kafka", build_operation_1),
+        ("api->multi-kakfa", build_operation_2),
+    ];
+    let mut operations: Vec<Box<dyn Operation>> = Vec::new();
+    let mut errors: Vec<String> = Vec::new();
+    let mut failed = 0;
+    for (name, build) in builders {
+        let mut operation_errors = Vec::new();
+        match build(args, &mut operation_errors) {
+            Some(operation) => operations.push(operation),
+            None => failed += 1,
+        }
+        errors.extend(
+            operation_errors
+                .into_iter()
+                .map(|e| format!("[{name}] {e}")),
+        );
     }
-    {
-        let reader = ApiReader::new("http://192.168.47.204:8000").with_type::<Value>();
-        let mut operation =
-            IntervalFanoutOperation::new("api->multi-kakfa", reader, Duration::from_secs(5u64));
-        operation.add_writer(KafkaWriter::<Value>::new("localhost:9092", "topic3"));
-        operation.add_writer(KafkaWriter::<Value>::new("localhost:9092", "topic4"));
-        operations.push(Box::new(operation));
+    if !errors.is_empty() {
+        anyhow::bail!(
+            "Failed to build {failed} operation(s):\n  {}",
+            errors.join("\n  ")
+        );
     }
-    Courier::new(operations)
+    Ok(Courier::new(operations))
 }
@@ -0,0 +1,204 @@
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, anyhow};
+use reqwest::{Client, RequestBuilder};
+use serde::Deserialize;
+use tokio::sync::Mutex;
+
+use crate::secret::Secret;
+
+/// Tokens are refreshed this long before they expire, so a request never goes
+/// out with a token that expires in flight.
+const TOKEN_REFRESH_MARGIN: Duration = Duration::from_secs(30);
+/// Used when the token endpoint does not say how long a token lasts.
+const DEFAULT_TOKEN_LIFETIME: Duration = Duration::from_secs(3600);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiKeyLocation {
+    Header,
+    Query,
+}
+
+/// How an HTTP client authenticates its requests.
+#[derive(Debug)]
+pub enum HttpAuth {
+    Bearer(Secret),
+    Basic {
+        username: String,
+        password: Secret,
+    },
+    ApiKey {
+        name: String,
+        value: Secret,
+        location: ApiKeyLocation,
+    },
+    OAuth2(OAuth2ClientCredentials),
+}
+
+impl HttpAuth {
+    pub fn bearer(token: impl Into<Secret>) -> Self {
+        HttpAuth::Bearer(token.into())
+    }
+
+    pub fn basic(username: &str, password: impl Into<Secret>) -> Self {
+        HttpAuth::Basic {
+            username: username.into(),
+            password: password.into(),
+        }
+    }
+
+    pub fn api_key(name: &str, value: impl Into<Secret>, location: ApiKeyLocation) -> Self {
+        HttpAuth::ApiKey {
+            name: name.into(),
+            value: value.into(),
+            location,
+        }
+    }
+
+    pub fn oauth2(token_url: &str, client_id: &str, client_secret: impl Into<Secret>) -> Self {
+        HttpAuth::OAuth2(OAuth2ClientCredentials::new(
+            token_url,
+            client_id,
+            client_secret,
+        ))
+    }
+
+    /// Adds scopes to an OAuth2 client-credentials grant. Other schemes are
+    /// returned unchanged.
+    pub fn with_scopes(self, scopes: Vec<&str>) -> Self {
+        match self {
+            HttpAuth::OAuth2(oauth2) => HttpAuth::OAuth2(oauth2.with_scopes(scopes)),
+            other => other,
+        }
+    }
+
+    /// Resolves every secret once, so missing environment variables or files
+    /// are reported when the client is built rather than on the first request.
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            HttpAuth::Bearer(token) => token.resolve().map(drop),
+            HttpAuth::Basic { password, .. } => password.resolve().map(drop),
+            HttpAuth::ApiKey { value, .. } => value.resolve().map(drop),
+            HttpAuth::OAuth2(oauth2) => oauth2.client_secret.resolve().map(drop),
+        }
+    }
+
+    pub async fn apply(&self, client: &Client, request: RequestBuilder) -> Result<RequestBuilder> {
+        Ok(match self {
+            HttpAuth::Bearer(token) => request.bearer_auth(token.resolve()?),
+            HttpAuth::Basic { username, password } => {
+                request.basic_auth(username, Some(password.resolve()?))
+            }
+            HttpAuth::ApiKey {
+                name,
+                value,
+                location: ApiKeyLocation::Header,
+            } => request.header(name, value.resolve()?),
+            HttpAuth::ApiKey {
+                name,
+                value,
+                location: ApiKeyLocation::Query,
+            } => request.query(&[(name, value.resolve()?)]),
+            HttpAuth::OAuth2(oauth2) => request.bearer_auth(oauth2.token(client).await?),
+        })
+    }
+
+    /// Whether the credentials are a token fetched at runtime, so that a
+    /// request rejected with 401 may succeed with a new one.
+    pub(crate) fn refreshes_token(&self) -> bool {
+        matches!(self, HttpAuth::OAuth2(_))
+    }
+
+    /// Forgets any cached token, e.g. after the server answered 401.
+    pub async fn invalidate(&self) {
+        if let HttpAuth::OAuth2(oauth2) = self {
+            *oauth2.cached.lock().await = None;
+        }
+    }
+}
+
+#[derive(Debug)]
+struct CachedToken {
+    access_token: String,
+    expires_at: Instant,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: Option<u64>,
+}
+
+/// OAuth2 client-credentials grant. The token is cached and fetched again
+/// shortly before it expires.
+#[derive(Debug)]
+pub struct OAuth2ClientCredentials {
+    token_url: String,
+    client_id: String,
+    client_secret: Secret,
+    scopes: Vec<String>,
+    cached: Mutex<Option<CachedToken>>,
+}
+
+impl OAuth2ClientCredentials {
+    pub fn new(token_url: &str, client_id: &str, client_secret: impl Into<Secret>) -> Self {
+        Self {
+            token_url: token_url.into(),
+            client_id: client_id.into(),
+            client_secret: client_secret.into(),
+            scopes: Vec::new(),
+            cached: Mutex::new(None),
+        }
+    }
+
+    pub fn with_scopes(mut self, scopes: Vec<&str>) -> Self {
+        self.scopes = scopes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    async fn token(&self, client: &Client) -> Result<String> {
+        let mut cached = self.cached.lock().await;
+        if let Some(token) = cached.as_ref()
+            && Instant::now() + TOKEN_REFRESH_MARGIN < token.expires_at
+        {
+            return Ok(token.access_token.clone());
+        }
+
+        log::debug!("Requesting OAuth2 token from {}", self.token_url);
+        let mut form = vec![("grant_type", "client_credentials".to_string())];
+        if !self.scopes.is_empty() {
+            form.push(("scope", self.scopes.join(" ")));
+        }
+
+        let response = client
+            .post(&self.token_url)
+            .basic_auth(&self.client_id, Some(self.client_secret.resolve()?))
+            .form(&form)
+            .send()
+            .await
+            .with_context(|| format!("Failed to request OAuth2 token from {}", self.token_url))?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!(
+                "OAuth2 token request to {} failed: HTTP {}",
+                self.token_url,
+                response.status()
+            ));
+        }
+
+        let token: TokenResponse = response
+            .json()
+            .await
+            .context("Failed to parse OAuth2 token response")?;
+        let lifetime = token
+            .expires_in
+            .map_or(DEFAULT_TOKEN_LIFETIME, Duration::from_secs);
+        log::debug!("Received OAuth2 token valid for {lifetime:?}");
+
+        *cached = Some(CachedToken {
+            access_token: token.access_token.clone(),
+            expires_at: Instant::now() + lifetime,
+        });
+        Ok(token.access_token)
+    }
+}
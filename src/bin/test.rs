@@ -1,9 +1,19 @@
 include!(concat!(env!("CARGO_MANIFEST_DIR"), "/generated.rs"));
 
+use courier::cli::{Command, USAGE};
+
 #[tokio::main]
-async fn main() {
+async fn main() -> anyhow::Result<()> {
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
 
-    let runner: Courier = courier_from_config();
+    let args = match Args::parse()? {
+        Command::Run(args) => args,
+        Command::Help => {
+            println!("{USAGE}");
+            return Ok(());
+        }
+    };
+    let runner: Courier = courier_from_config(&args)?;
     runner.run().await;
+    Ok(())
 }
@@ -0,0 +1,61 @@
+use anyhow::{Context, Result, bail};
+
+use crate::readers::kafka::{EndPosition, StartPosition};
+
+pub const USAGE: &str = "\
+Options:
+  --start-position <POSITION>  Where every Kafka reader starts: earliest, latest,
+                               timestamp:<millis | RFC 3339> or
+                               offsets:<topic>:<partition>:<offset>[,...]
+  --end-position <POSITION>    Makes every Kafka reader stop at: high-watermark,
+                               timestamp:<millis | RFC 3339> or
+                               offsets:<topic>:<partition>:<offset>[,...]
+  -h, --help                   Print this help";
+
+/// What the command line asks for.
+#[derive(Debug)]
+pub enum Command {
+    Run(Args),
+    /// `-h` or `--help`: print [`USAGE`] and exit.
+    Help,
+}
+
+/// Command line overrides for operations built from `config.toml`.
+#[derive(Debug, Default)]
+pub struct Args {
+    pub start_position: Option<StartPosition>,
+    pub end_position: Option<EndPosition>,
+}
+
+impl Args {
+    pub fn parse() -> Result<Command> {
+        Self::parse_from(std::env::args().skip(1))
+    }
+
+    pub fn parse_from(args: impl IntoIterator<Item = String>) -> Result<Command> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+            let mut value = || {
+                inline_value
+                    .clone()
+                    .or_else(|| args.next())
+                    .with_context(|| format!("Missing value for {flag}"))
+            };
+
+            match flag.as_str() {
+                "--start-position" => parsed.start_position = Some(value()?.parse()?),
+                "--end-position" => parsed.end_position = Some(value()?.parse()?),
+                "-h" | "--help" => return Ok(Command::Help),
+                _ => bail!("Unknown argument '{flag}'\n\n{USAGE}"),
+            }
+        }
+
+        Ok(Command::Run(parsed))
+    }
+}
@@ -0,0 +1,290 @@
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{Context, Result, bail};
+use quick_xml::escape::resolve_predefined_entity;
+use quick_xml::events::{BytesStart, Event};
+use serde_json::{Map, Value};
+
+/// How a text payload is turned into JSON.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    Json,
+    /// The whole payload as one string.
+    Text,
+    /// An array with one object per row, keyed by the header row. Values are
+    /// kept as strings.
+    Csv,
+    /// An array with one value per non-empty line.
+    Ndjson,
+    /// An object keyed by the root element. Attributes become `@name` fields,
+    /// text next to child elements becomes `#text` and repeated children
+    /// become arrays.
+    Xml,
+}
+
+impl Format {
+    pub fn parse(&self, text: &str) -> Result<Value> {
+        match self {
+            Format::Json => Ok(serde_json::from_str(text)?),
+            Format::Text => Ok(Value::String(text.into())),
+            Format::Csv => parse_csv(text),
+            Format::Ndjson => text
+                .lines()
+                .enumerate()
+                .filter(|(_, line)| !line.trim().is_empty())
+                .map(|(i, line)| {
+                    serde_json::from_str(line)
+                        .with_context(|| format!("Invalid JSON on line {}", i + 1))
+                })
+                .collect::<Result<_>>()
+                .map(Value::Array),
+            Format::Xml => parse_xml(text),
+        }
+    }
+
+    /// Splits a payload into records: one per line for NDJSON and text, one
+    /// per row for CSV, one per element of a JSON array, or the whole
+    /// document for other JSON and XML.
+    pub fn parse_records(&self, text: &str) -> Result<Vec<Value>> {
+        match self {
+            Format::Text => Ok(text
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(|line| Value::String(line.into()))
+                .collect()),
+            Format::Json | Format::Ndjson | Format::Csv => match self.parse(text)? {
+                Value::Array(records) => Ok(records),
+                other => Ok(vec![other]),
+            },
+            Format::Xml => Ok(vec![self.parse(text)?]),
+        }
+    }
+
+    /// Guesses the format of a file from its extension.
+    pub fn from_extension(path: &Path) -> Option<Format> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "json" => Some(Format::Json),
+            "ndjson" | "jsonl" => Some(Format::Ndjson),
+            "csv" => Some(Format::Csv),
+            "xml" => Some(Format::Xml),
+            "txt" => Some(Format::Text),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "json" => Ok(Format::Json),
+            "text" => Ok(Format::Text),
+            "csv" => Ok(Format::Csv),
+            "ndjson" | "jsonl" => Ok(Format::Ndjson),
+            "xml" => Ok(Format::Xml),
+            _ => bail!("Unknown format '{s}', expected json, text, csv, ndjson or xml"),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Format::Json => "json",
+            Format::Text => "text",
+            Format::Csv => "csv",
+            Format::Ndjson => "ndjson",
+            Format::Xml => "xml",
+        };
+        write!(f, "{name}")
+    }
+}
+
+fn parse_csv(text: &str) -> Result<Value> {
+    let mut reader = csv::Reader::from_reader(text.as_bytes());
+    let headers = reader.headers()?.clone();
+    reader
+        .records()
+        .map(|record| {
+            let record = record?;
+            let row: Map<String, Value> = headers
+                .iter()
+                .zip(record.iter())
+                .map(|(name, value)| (name.to_string(), Value::String(value.into())))
+                .collect();
+            Ok(Value::Object(row))
+        })
+        .collect::<Result<_>>()
+        .map(Value::Array)
+}
+
+struct Element {
+    name: String,
+    fields: Map<String, Value>,
+    text: String,
+}
+
+impl Element {
+    fn new(start: &BytesStart) -> Result<Self> {
+        let mut fields = Map::new();
+        for attribute in start.attributes() {
+            let attribute = attribute?;
+            let name = String::from_utf8_lossy(attribute.key.as_ref());
+            let value = attribute.unescape_value()?;
+            fields.insert(format!("@{name}"), Value::String(value.into_owned()));
+        }
+        Ok(Self {
+            name: String::from_utf8_lossy(start.name().as_ref()).into_owned(),
+            fields,
+            text: String::new(),
+        })
+    }
+
+    fn add_child(&mut self, name: String, value: Value) {
+        match self.fields.get_mut(&name) {
+            Some(Value::Array(values)) => values.push(value),
+            Some(existing) => *existing = Value::Array(vec![existing.take(), value]),
+            None => {
+                self.fields.insert(name, value);
+            }
+        }
+    }
+
+    fn into_value(mut self) -> Value {
+        let text = self.text.trim();
+        if self.fields.is_empty() {
+            return match text {
+                "" => Value::Null,
+                text => Value::String(text.into()),
+            };
+        }
+        if !text.is_empty() {
+            self.fields
+                .insert("#text".into(), Value::String(text.into()));
+        }
+        Value::Object(self.fields)
+    }
+}
+
+fn parse_xml(text: &str) -> Result<Value> {
+    let mut reader = quick_xml::Reader::from_str(text);
+    let mut stack: Vec<Element> = Vec::new();
+    let mut root = Map::new();
+
+    let mut close = |stack: &mut Vec<Element>, element: Element| {
+        let name = element.name.clone();
+        let value = element.into_value();
+        match stack.last_mut() {
+            Some(parent) => parent.add_child(name, value),
+            None => {
+                root.insert(name, value);
+            }
+        }
+    };
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(start) => stack.push(Element::new(&start)?),
+            Event::Empty(start) => {
+                let element = Element::new(&start)?;
+                close(&mut stack, element);
+            }
+            Event::End(_) => {
+                let element = stack.pop().context("Unbalanced XML end tag")?;
+                close(&mut stack, element);
+            }
+            Event::Text(text) => {
+                if let Some(element) = stack.last_mut() {
+                    element.text.push_str(&text.xml_content()?);
+                }
+            }
+            Event::CData(data) => {
+                if let Some(element) = stack.last_mut() {
+                    element.text.push_str(&data.xml_content()?);
+                }
+            }
+            Event::GeneralRef(reference) => {
+                let Some(element) = stack.last_mut() else {
+                    continue;
+                };
+                if let Some(c) = reference.resolve_char_ref()? {
+                    element.text.push(c);
+                } else {
+                    let name = reference.xml_content()?;
+                    let resolved = resolve_predefined_entity(&name)
+                        .with_context(|| format!("Unknown XML entity '&{name};'"))?;
+                    element.text.push_str(resolved);
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    if !stack.is_empty() {
+        bail!("Unclosed XML element '{}'", stack[0].name);
+    }
+    Ok(Value::Object(root))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn splits_json_arrays_into_records() {
+        let records = Format::Json
+            .parse_records(r#"[{"id": 1}, {"id": 2}]"#)
+            .unwrap();
+        assert_eq!(records, vec![json!({ "id": 1 }), json!({ "id": 2 })]);
+
+        let records = Format::Json.parse_records(r#"{"id": 1}"#).unwrap();
+        assert_eq!(records, vec![json!({ "id": 1 })]);
+    }
+
+    #[test]
+    fn splits_lines_and_rows_into_records() {
+        let records = Format::Ndjson
+            .parse_records("{\"id\": 1}\n\n{\"id\": 2}\n")
+            .unwrap();
+        assert_eq!(records, vec![json!({ "id": 1 }), json!({ "id": 2 })]);
+
+        let records = Format::Csv.parse_records("id,name\n1,a\n2,b\n").unwrap();
+        assert_eq!(
+            records,
+            vec![
+                json!({ "id": "1", "name": "a" }),
+                json!({ "id": "2", "name": "b" })
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_xml() {
+        let xml =
+            r#"<order id="7"><item>a</item><item>b &amp; c</item><note>hi<b/></note></order>"#;
+        assert_eq!(
+            Format::Xml.parse(xml).unwrap(),
+            json!({
+                "order": {
+                    "@id": "7",
+                    "item": ["a", "b & c"],
+                    "note": { "b": null, "#text": "hi" }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_unbalanced_xml() {
+        assert!(Format::Xml.parse("<order><item>a</item>").is_err());
+        assert!(Format::Xml.parse("<order></item>").is_err());
+    }
+}
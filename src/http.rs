@@ -0,0 +1,79 @@
+use anyhow::Result;
+use reqwest::{RequestBuilder, Response, StatusCode};
+
+use crate::auth::HttpAuth;
+use crate::rate_limit::{RateLimiter, backoff, retry_after};
+
+/// Sends the request built by `request`, waiting for `rate_limiter` first.
+/// While the server answers `429 Too Many Requests` or a `5xx` status, the
+/// request is retried up to `max_retries` times. After a `401 Unauthorized`,
+/// the cached token of `auth` is dropped and the request is retried once with
+/// a new one. Returns the last response, whatever its status.
+pub(crate) async fn send<F>(
+    url: &str,
+    max_retries: u32,
+    rate_limiter: Option<&RateLimiter>,
+    auth: Option<&HttpAuth>,
+    request: impl Fn() -> F,
+) -> Result<Response>
+where
+    F: Future<Output = Result<RequestBuilder>>,
+{
+    let mut attempt = 0;
+    let mut reauthenticated = false;
+    loop {
+        if let Some(rate_limiter) = rate_limiter {
+            rate_limiter.acquire().await;
+        }
+
+        let response = match request().await?.send().await {
+            Ok(resp) => resp,
+            Err(e) => {
+                if e.is_timeout() {
+                    log::error!("Request timeout for {url}");
+                } else if e.is_connect() {
+                    log::error!("Connection failed for {url}: {e}");
+                } else if e.is_request() {
+                    log::error!("Request error for {url}: {e}");
+                } else {
+                    log::error!("Unknown error for {url}: {e}");
+                }
+                return Err(e.into());
+            }
+        };
+        let status = response.status();
+        log::trace!("Response received from {url} with status: {status}");
+
+        if status == StatusCode::UNAUTHORIZED
+            && let Some(auth) = auth
+        {
+            auth.invalidate().await;
+            if auth.refreshes_token() && !reauthenticated {
+                reauthenticated = true;
+                log::warn!("HTTP {status} from {url}, retrying with a new token");
+                continue;
+            }
+            return Ok(response);
+        }
+        if status != StatusCode::TOO_MANY_REQUESTS && !status.is_server_error() {
+            return Ok(response);
+        }
+
+        let delay = retry_after(response.headers()).unwrap_or_else(|| backoff(attempt));
+        if let Some(rate_limiter) = rate_limiter {
+            // Later requests, and other clients sharing the limiter, back off too.
+            rate_limiter.pause(delay).await;
+        }
+
+        if attempt >= max_retries {
+            return Ok(response);
+        }
+        attempt += 1;
+        log::warn!(
+            "HTTP {status} from {url}, retrying in {delay:?} (attempt {attempt}/{max_retries})"
+        );
+        if rate_limiter.is_none() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
@@ -1,9 +1,22 @@
 use futures::future;
 use operations::Operation;
 
+pub mod auth;
+pub mod cli;
+pub mod format;
+mod http;
+pub mod mqtt;
+pub mod nats;
 pub mod operations;
+pub mod rate_limit;
 pub mod readers;
+pub mod redis;
 pub mod schemas;
+pub mod secret;
+pub mod sql;
+pub mod state;
+pub mod template;
+pub mod watermark;
 pub mod writers;
 
 pub struct Courier {
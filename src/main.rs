@@ -1,13 +1,11 @@
 use std::time::Duration;
 
-use courier::readers::kafka::KafkaReader;
 use courier::writers::kafka::KafkaWriter;
 use serde::{Deserialize, Serialize};
 
-use courier::operations::{IntervalFanoutOperation, IntervalOperation, StreamOperation};
+use courier::operations::IntervalFanoutOperation;
 use courier::readers::api::ApiReader;
 use courier::{Courier, courier};
-use serde_json::Value;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ApiItalo {
@@ -24,7 +22,6 @@ async fn main() {
 
     let writer: KafkaWriter<ApiItalo> = KafkaWriter::new("localhost:9092", "simple-producer2");
     let writer2: KafkaWriter<ApiItalo> = KafkaWriter::new("localhost:9092", "simple-producer");
-    let writer3: KafkaWriter<ApiItalo> = KafkaWriter::new("localhost:9092", "simple-producer");
 
     // let operation1 = StreamOperation::new("kafka-kafka", kafka_reader, writer);
     // let operation2 = IntervalOperation::new(
@@ -0,0 +1,93 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+pub use rumqttc::QoS;
+use rumqttc::{AsyncClient, EventLoop, MqttOptions};
+use tokio::task::JoinHandle;
+
+use crate::secret::Secret;
+
+const DEFAULT_PORT: u16 = 1883;
+/// Publishes and acknowledgements queued for the event loop.
+const REQUEST_CAPACITY: usize = 100;
+/// Pause after losing the connection before reconnecting.
+pub(crate) const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Connection settings shared by the MQTT reader and writer. The broker is
+/// `host[:port]`, on port 1883 unless given.
+pub(crate) struct MqttSettings {
+    broker: String,
+    client_id: String,
+    credentials: Option<(String, Secret)>,
+    keep_alive: Option<Duration>,
+    persistent_session: bool,
+}
+
+impl MqttSettings {
+    pub(crate) fn new(broker: &str, client_id: &str) -> Self {
+        Self {
+            broker: broker.into(),
+            client_id: client_id.into(),
+            credentials: None,
+            keep_alive: None,
+            persistent_session: false,
+        }
+    }
+
+    pub(crate) fn set_credentials(&mut self, username: &str, password: Secret) {
+        self.credentials = Some((username.into(), password));
+    }
+
+    pub(crate) fn set_keep_alive(&mut self, keep_alive: Duration) {
+        self.keep_alive = Some(keep_alive);
+    }
+
+    pub(crate) fn set_persistent_session(&mut self) {
+        self.persistent_session = true;
+    }
+
+    pub(crate) fn options(&self) -> Result<MqttOptions> {
+        if self.client_id.is_empty() {
+            bail!("MQTT client needs a client ID");
+        }
+        let (host, port) = match self.broker.rsplit_once(':') {
+            Some((host, port)) => {
+                let port = port
+                    .parse()
+                    .with_context(|| format!("Invalid MQTT broker '{}'", self.broker))?;
+                (host, port)
+            }
+            None => (self.broker.as_str(), DEFAULT_PORT),
+        };
+        if host.is_empty() {
+            bail!("Invalid MQTT broker '{}'", self.broker);
+        }
+
+        let mut options = MqttOptions::new(&self.client_id, host, port);
+        options.set_clean_session(!self.persistent_session);
+        if let Some(keep_alive) = self.keep_alive {
+            options.set_keep_alive(keep_alive);
+        }
+        if let Some((username, password)) = &self.credentials {
+            let password = password
+                .resolve()
+                .context("Failed to resolve MQTT password")?;
+            options.set_credentials(username, password);
+        }
+        Ok(options)
+    }
+}
+
+pub(crate) fn client(options: MqttOptions) -> (AsyncClient, EventLoop) {
+    AsyncClient::new(options, REQUEST_CAPACITY)
+}
+
+/// The task polling an event loop, which keeps the connection alive. It is
+/// stopped when dropped.
+pub(crate) struct EventLoopTask(pub(crate) JoinHandle<()>);
+
+impl Drop for EventLoopTask {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
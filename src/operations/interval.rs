@@ -2,7 +2,7 @@ use async_trait::async_trait;
 use std::time::{Duration, Instant};
 use tokio::time::{MissedTickBehavior, interval};
 
-use super::Operation;
+use super::{Operation, WriteMode};
 use crate::readers::Reader;
 use crate::writers::Writer;
 
@@ -14,6 +14,7 @@ where
     reader: R,
     writer: W,
     interval: Duration,
+    write_mode: WriteMode,
     id: String,
 }
 
@@ -28,9 +29,22 @@ where
             reader,
             writer,
             interval,
+            write_mode: WriteMode::default(),
             id: id.into(),
         }
     }
+
+    pub fn with_write_mode(mut self, write_mode: WriteMode) -> Self {
+        self.write_mode = write_mode;
+        self
+    }
+
+    /// Lets the reader move past the records it returned last.
+    async fn commit(&self) {
+        if let Err(e) = self.reader.commit().await {
+            log::error!("[{}] Failed to commit read: {:?}", self.id, e);
+        }
+    }
 }
 
 #[async_trait]
@@ -54,14 +68,44 @@ where
             let start = Instant::now();
 
             log::info!("[{}] Reading data", self.id);
-            match self.reader.read().await {
-                Ok(data) => {
+            match self.reader.read_batch().await {
+                Ok(records) if records.is_empty() => {
+                    log::info!("[{}] No new data to write", self.id);
+                    self.commit().await;
+                }
+                Ok(records) => {
                     log::debug!("[{}] Read completed in {:?}", self.id, start.elapsed());
-                    log::info!("[{}] Successfully read data, writing...", self.id);
-                    if let Err(e) = self.writer.write(data.into()).await {
-                        log::error!("[{}] Failed to write data: {:?}", self.id, e);
-                    } else {
-                        log::info!("[{}] Successfully wrote data", self.id);
+                    log::info!(
+                        "[{}] Successfully read {} record(s), writing...",
+                        self.id,
+                        records.len()
+                    );
+                    let written = match self.write_mode {
+                        WriteMode::Individual => {
+                            let mut written = true;
+                            for data in records {
+                                if let Err(e) = self.writer.write(data.into()).await {
+                                    log::error!("[{}] Failed to write data: {:?}", self.id, e);
+                                    written = false;
+                                } else {
+                                    log::info!("[{}] Successfully wrote data", self.id);
+                                }
+                            }
+                            written
+                        }
+                        WriteMode::Batch => {
+                            let batch = records.into_iter().map(Into::into).collect();
+                            if let Err(e) = self.writer.write_batch(batch).await {
+                                log::error!("[{}] Failed to write batch: {:?}", self.id, e);
+                                false
+                            } else {
+                                log::info!("[{}] Successfully wrote batch", self.id);
+                                true
+                            }
+                        }
+                    };
+                    if written {
+                        self.commit().await;
                     }
                 }
                 Err(e) => {
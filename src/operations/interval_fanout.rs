@@ -5,13 +5,14 @@ use async_trait::async_trait;
 use futures::future;
 use tokio::time::{MissedTickBehavior, interval};
 
-use super::Operation;
+use super::{Operation, WriteMode};
 use crate::readers::Reader;
 use crate::writers::Writer;
 
 #[async_trait]
 trait WriterBox<T>: Send + Sync {
     async fn write(&self, item: &T) -> Result<()>;
+    async fn write_batch(&self, items: &[T]) -> Result<()>;
 }
 
 #[async_trait]
@@ -25,6 +26,11 @@ where
         let converted = W::Item::from(item.clone());
         self.write(converted).await
     }
+
+    async fn write_batch(&self, items: &[T]) -> Result<()> {
+        let converted = items.iter().cloned().map(W::Item::from).collect();
+        Writer::write_batch(self, converted).await
+    }
 }
 
 pub struct IntervalFanoutOperation<R>
@@ -34,6 +40,7 @@ where
     reader: R,
     writers: Vec<Box<dyn WriterBox<R::Item>>>,
     interval: Duration,
+    write_mode: WriteMode,
     id: String,
 }
 
@@ -47,10 +54,16 @@ where
             reader,
             writers: Vec::new(),
             interval,
+            write_mode: WriteMode::default(),
             id: id.into(),
         }
     }
 
+    pub fn with_write_mode(mut self, write_mode: WriteMode) -> Self {
+        self.write_mode = write_mode;
+        self
+    }
+
     pub fn add_writer<W>(&mut self, writer: W)
     where
         W: Writer + 'static,
@@ -67,6 +80,13 @@ where
         self.add_writer(writer);
         self
     }
+
+    /// Lets the reader move past the records it returned last.
+    async fn commit(&self) {
+        if let Err(e) = self.reader.commit().await {
+            log::error!("[{}] Failed to commit read: {:?}", self.id, e);
+        }
+    }
 }
 
 #[async_trait]
@@ -98,22 +118,66 @@ where
             let start = Instant::now();
 
             log::info!("[{}] Reading data", self.id);
-            match self.reader.read().await {
-                Ok(data) => {
+            match self.reader.read_batch().await {
+                Ok(records) if records.is_empty() => {
+                    log::info!("[{}] No new data to write", self.id);
+                    self.commit().await;
+                }
+                Ok(records) => {
                     log::debug!("[{}] Read completed in {:?}", self.id, start.elapsed());
-                    log::info!("[{}] Successfully read data, writing...", self.id);
-
-                    let write_futures = self.writers.iter().map(|writer| {
-                        let data_clone = data.clone();
-                        async move {
-                            if let Err(e) = writer.write((&data_clone).into()).await {
-                                log::error!("[{}] Failed to write data: {:?}", self.id, e);
-                            } else {
-                                log::info!("[{}] Successfully wrote data", self.id);
+                    log::info!(
+                        "[{}] Successfully read {} record(s), writing...",
+                        self.id,
+                        records.len()
+                    );
+
+                    // The read is only committed once every writer has
+                    // written every record.
+                    let written = match self.write_mode {
+                        WriteMode::Individual => {
+                            let mut written = true;
+                            for data in records {
+                                let write_futures = self.writers.iter().map(|writer| {
+                                    let data_clone = data.clone();
+                                    async move {
+                                        if let Err(e) = writer.write(&data_clone).await {
+                                            log::error!(
+                                                "[{}] Failed to write data: {:?}",
+                                                self.id,
+                                                e
+                                            );
+                                            false
+                                        } else {
+                                            log::info!("[{}] Successfully wrote data", self.id);
+                                            true
+                                        }
+                                    }
+                                });
+                                let results = future::join_all(write_futures).await;
+                                written &= results.into_iter().all(|ok| ok);
                             }
+                            written
+                        }
+                        WriteMode::Batch => {
+                            let write_futures = self.writers.iter().map(|writer| {
+                                let records = &records;
+                                async move {
+                                    if let Err(e) = writer.write_batch(records).await {
+                                        log::error!("[{}] Failed to write batch: {:?}", self.id, e);
+                                        false
+                                    } else {
+                                        log::info!("[{}] Successfully wrote batch", self.id);
+                                        true
+                                    }
+                                }
+                            });
+                            let results = future::join_all(write_futures).await;
+                            results.into_iter().all(|ok| ok)
                         }
-                    });
-                    future::join_all(write_futures).await;
+                    };
+                    if written {
+                        self.commit().await;
+                    }
                 }
                 Err(e) => {
                     log::error!("[{}] Failed to read data: {:?}", self.id, e);
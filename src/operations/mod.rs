@@ -8,6 +8,16 @@ pub use interval::IntervalOperation;
 pub use interval_fanout::IntervalFanoutOperation;
 pub use stream::StreamOperation;
 
+/// How interval operations hand the records of one read to their writers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WriteMode {
+    /// One `write` call per record.
+    #[default]
+    Individual,
+    /// A single `write_batch` call with every record.
+    Batch,
+}
+
 #[async_trait]
 pub trait Operation: Send + Sync {
     async fn run(&self);
@@ -57,14 +57,26 @@ where
                 Ok(_) => {
                     log::info!("[{}] Successfully wrote data", self.id);
                     log::debug!("[{}] Write took {:.2?}", self.id, write_start.elapsed());
+                    if let Err(e) = self.reader.acknowledge().await {
+                        log::error!("[{}] Failed to acknowledge data: {:?}", self.id, e);
+                    }
+                }
+                Err(e) => {
+                    log::error!("[{}] Failed to write data: {:?}", self.id, e);
+                    if let Err(e) = self.reader.reject().await {
+                        log::error!("[{}] Failed to reject data: {:?}", self.id, e);
+                    }
                 }
-                Err(e) => log::error!("[{}] Failed to write data: {:?}", self.id, e),
             }
 
             log::info!("[{}] Waiting for incoming data", self.id);
             waiting_time = Instant::now();
         }
 
-        log::warn!("[{}] Stream ended unexpectedly", self.id);
+        if self.reader.is_bounded() {
+            log::info!("[{}] Stream completed", self.id);
+        } else {
+            log::warn!("[{}] Stream ended unexpectedly", self.id);
+        }
     }
 }
@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, bail};
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use tokio::sync::Mutex;
+
+/// Backoff after the first throttled or failed attempt, doubled on each retry
+/// up to `MAX_BACKOFF`.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+/// Longest `Retry-After` honoured, so that a server cannot hold requests back
+/// for days.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(300);
+
+/// Limiters shared by every client talking to the same host.
+static SHARED: OnceLock<std::sync::Mutex<HashMap<String, Arc<RateLimiter>>>> = OnceLock::new();
+
+/// A token bucket holding up to `burst` requests, refilled at
+/// `requests_per_sec`. The limiter can also be paused, e.g. when a server
+/// answers `429 Too Many Requests`, which holds back every request until the
+/// pause is over.
+#[derive(Debug)]
+pub struct RateLimiter {
+    rate: Option<f64>,
+    burst: f64,
+    bucket: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+    paused_until: Option<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(requests_per_sec: f64, burst: u32) -> Result<Self> {
+        if !requests_per_sec.is_finite() || requests_per_sec <= 0.0 {
+            bail!(
+                "Rate limit must be a positive number of requests per second, got {requests_per_sec}"
+            );
+        }
+        Ok(Self::with_rate(Some(requests_per_sec), burst.max(1) as f64))
+    }
+
+    /// A limiter that never limits the rate, but can still be paused.
+    pub fn unlimited() -> Self {
+        Self::with_rate(None, 1.0)
+    }
+
+    /// Returns the limiter shared by everything calling `host`, creating it
+    /// with these settings if this is the first one. Fails if the host already
+    /// has a limiter with other settings.
+    pub fn shared(host: &str, requests_per_sec: f64, burst: u32) -> Result<Arc<Self>> {
+        let limiter = Self::new(requests_per_sec, burst)?;
+        let limiters = SHARED.get_or_init(Default::default);
+        let mut limiters = limiters.lock().unwrap();
+        if let Some(existing) = limiters.get(host) {
+            if existing.rate != limiter.rate || existing.burst != limiter.burst {
+                bail!(
+                    "Shared rate limit for {host} is already {} request(s)/s with a burst of {}, got {requests_per_sec} with a burst of {burst}",
+                    existing.rate.unwrap_or(f64::INFINITY),
+                    existing.burst
+                );
+            }
+            return Ok(existing.clone());
+        }
+        log::debug!("Created shared rate limiter for {host}");
+        let limiter = Arc::new(limiter);
+        limiters.insert(host.into(), limiter.clone());
+        Ok(limiter)
+    }
+
+    fn with_rate(rate: Option<f64>, burst: f64) -> Self {
+        Self {
+            rate,
+            burst,
+            bucket: Mutex::new(Bucket {
+                tokens: burst,
+                refilled_at: Instant::now(),
+                paused_until: None,
+            }),
+        }
+    }
+
+    /// Waits until a request may be sent.
+    pub async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().await;
+                let now = Instant::now();
+
+                match bucket.paused_until {
+                    Some(until) if until > now => until - now,
+                    _ => {
+                        bucket.paused_until = None;
+                        let Some(rate) = self.rate else {
+                            return;
+                        };
+
+                        let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+                        bucket.tokens = (bucket.tokens + elapsed * rate).min(self.burst);
+                        bucket.refilled_at = now;
+
+                        if bucket.tokens >= 1.0 {
+                            bucket.tokens -= 1.0;
+                            return;
+                        }
+                        Duration::from_secs_f64((1.0 - bucket.tokens) / rate)
+                    }
+                }
+            };
+            log::trace!("Rate limited, waiting {wait:?}");
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Holds back every request for `delay`, unless already paused for longer.
+    pub async fn pause(&self, delay: Duration) {
+        let until = Instant::now() + delay;
+        let mut bucket = self.bucket.lock().await;
+        if bucket.paused_until.is_none_or(|paused| paused < until) {
+            bucket.paused_until = Some(until);
+        }
+    }
+}
+
+/// How long to wait before retry number `attempt + 1`, when the server did
+/// not say with `Retry-After`.
+pub fn backoff(attempt: u32) -> Duration {
+    (INITIAL_BACKOFF * 2u32.saturating_pow(attempt)).min(MAX_BACKOFF)
+}
+
+/// Reads a `Retry-After` header given either in seconds or as an HTTP date,
+/// capped at `MAX_RETRY_AFTER`.
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    let delay = match value.parse::<u64>() {
+        Ok(secs) => Duration::from_secs(secs),
+        Err(_) => {
+            let date = DateTime::parse_from_rfc2822(value).ok()?;
+            (date.with_timezone(&Utc) - Utc::now())
+                .to_std()
+                .unwrap_or_default()
+        }
+    };
+    if delay > MAX_RETRY_AFTER {
+        log::warn!("Retry-After of {delay:?} is too long, waiting {MAX_RETRY_AFTER:?} instead");
+        return Some(MAX_RETRY_AFTER);
+    }
+    Some(delay)
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::HeaderValue;
+
+    use super::*;
+
+    fn headers(retry_after: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_str(retry_after).unwrap());
+        headers
+    }
+
+    #[test]
+    fn rejects_invalid_rates() {
+        for rate in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(RateLimiter::new(rate, 1).is_err(), "{rate}");
+        }
+        assert!(RateLimiter::new(2.0, 1).is_ok());
+    }
+
+    #[test]
+    fn rejects_shared_limiter_with_other_settings() {
+        let host = "rate-limit-test.example";
+        RateLimiter::shared(host, 5.0, 2).unwrap();
+        assert!(RateLimiter::shared(host, 5.0, 2).is_ok());
+        assert!(RateLimiter::shared(host, 10.0, 2).is_err());
+    }
+
+    #[test]
+    fn reads_retry_after() {
+        assert_eq!(retry_after(&headers("12")), Some(Duration::from_secs(12)));
+        assert_eq!(
+            retry_after(&headers("Thu, 01 Jan 1970 00:00:00 GMT")),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(retry_after(&headers("soon")), None);
+        assert_eq!(retry_after(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn caps_retry_after() {
+        assert_eq!(retry_after(&headers("999999")), Some(MAX_RETRY_AFTER));
+    }
+
+    #[test]
+    fn doubles_backoff_up_to_the_limit() {
+        assert_eq!(backoff(0), INITIAL_BACKOFF);
+        assert_eq!(backoff(2), INITIAL_BACKOFF * 4);
+        assert_eq!(backoff(40), MAX_BACKOFF);
+    }
+}
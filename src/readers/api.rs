@@ -1,74 +1,883 @@
+use std::collections::hash_map::DefaultHasher;
 use std::fmt::Debug;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use anyhow::{Result, anyhow};
+use anyhow::{Context, Result, anyhow};
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+pub use reqwest::Method;
+use reqwest::header::{ETAG, HeaderMap, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, LINK};
+use reqwest::{Client, Proxy, StatusCode, Url};
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{Map, Value, json};
 
+use crate::auth::HttpAuth;
+use crate::format::Format;
+use crate::http;
+use crate::rate_limit::RateLimiter;
 use crate::readers::Reader;
+use crate::schemas::json_path;
+use crate::template::Template;
+pub use crate::watermark::Watermark;
+
+const DEFAULT_MAX_PAGES: usize = 100;
+const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// How an `ApiReader` walks a paginated endpoint. Every page is read on each
+/// tick, up to the reader's page limit.
+#[derive(Debug, Clone)]
+pub enum Pagination {
+    /// Sends a page number in `param`, starting at `start` and stopping at the
+    /// first page without records.
+    PageNumber { param: String, start: u64 },
+    /// Sends a record offset in `param`, starting at `start`, advanced by the
+    /// records on each page and stopping at the first page without records.
+    Offset { param: String, start: u64 },
+    /// Sends back the cursor found at `cursor_path` in each response as
+    /// `param`, stopping when there is no cursor.
+    Cursor { cursor_path: String, param: String },
+    /// Follows the `rel="next"` URL of the `Link` header (RFC 5988).
+    LinkHeader,
+    /// Follows the next-page URL found at `path` in each response.
+    NextUrl { path: String },
+}
+
+impl Pagination {
+    fn start(&self) -> u64 {
+        match self {
+            Pagination::PageNumber { start, .. } | Pagination::Offset { start, .. } => *start,
+            _ => 0,
+        }
+    }
+
+    fn first_page_query(&self) -> Option<(&str, String)> {
+        match self {
+            Pagination::PageNumber { param, start } | Pagination::Offset { param, start } => {
+                Some((param.as_str(), start.to_string()))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Skips responses that have not changed since the last poll. The reader
+/// sends the `ETag` and `Last-Modified` of the previous response back as
+/// `If-None-Match` and `If-Modified-Since`, treats `304 Not Modified` as
+/// unchanged, and otherwise compares a hash of the response with the last one.
+///
+/// Conditional requests are only made to endpoints that are not paginated.
+#[derive(Debug, Clone, Default)]
+pub struct ChangeDetection {
+    fields: Vec<String>,
+}
+
+impl ChangeDetection {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only hashes these dotted paths of each record, so that e.g. a
+    /// `fetched_at` field does not count as a change.
+    pub fn with_fields(mut self, fields: Vec<String>) -> Self {
+        self.fields = fields;
+        self
+    }
+
+    fn hash(&self, data: &Value) -> u64 {
+        let subset = |record: &Value| -> Value {
+            self.fields
+                .iter()
+                .map(|field| json_path(record, field).cloned().unwrap_or(Value::Null))
+                .collect()
+        };
+        let hashed = match data {
+            _ if self.fields.is_empty() => data.clone(),
+            Value::Array(records) => records.iter().map(subset).collect(),
+            record => subset(record),
+        };
+
+        let mut hasher = DefaultHasher::new();
+        hashed.to_string().hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// Wraps each payload in an object describing the response it came from:
+/// `{ "url": ..., "status": ..., "headers": {...}, "fetched_at": ..., "body": ... }`.
+/// Paginated reads describe the first page.
+#[derive(Debug, Clone, Default)]
+pub struct Envelope {
+    headers: Vec<String>,
+}
+
+impl Envelope {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Response headers copied into the envelope, e.g. `content-type`.
+    pub fn with_headers(mut self, headers: Vec<String>) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    fn select_headers(&self, headers: &HeaderMap) -> Map<String, Value> {
+        self.headers
+            .iter()
+            .filter_map(|name| {
+                let value = headers.get(name.as_str())?.to_str().ok()?;
+                Some((name.to_lowercase(), Value::String(value.into())))
+            })
+            .collect()
+    }
+
+    fn wrap(&self, body: Value, meta: &ResponseMeta) -> Value {
+        json!({
+            "url": meta.url.as_str(),
+            "status": meta.status,
+            "headers": meta.headers,
+            "fetched_at": meta.fetched_at.to_rfc3339(),
+            "body": body,
+        })
+    }
+}
+
+/// What the reader remembers about the last response it returned.
+#[derive(Debug, Clone, Default)]
+struct LastResponse {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    hash: Option<u64>,
+}
+
+/// What the last read reached, kept until its records have been written.
+#[derive(Debug, Default)]
+struct PendingRead {
+    watermark: Option<Value>,
+    response: Option<LastResponse>,
+}
 
 pub struct ApiReader<T> {
+    client: Client,
     url: String,
+    method: Method,
+    headers: Vec<(String, Template)>,
+    query: Vec<(String, Template)>,
+    body: Option<Value>,
+    auth: Option<HttpAuth>,
+    records_path: Option<String>,
+    split_records: bool,
+    pagination: Option<Pagination>,
+    max_pages: usize,
+    watermark: Option<Watermark>,
+    watermark_value: Mutex<Option<Value>>,
+    pending: Mutex<PendingRead>,
+    change_detection: Option<ChangeDetection>,
+    last_response: Mutex<LastResponse>,
+    rate_limiter: Arc<RateLimiter>,
+    max_retries: u32,
+    format: Format,
+    envelope: Option<Envelope>,
     _phantom: std::marker::PhantomData<T>,
 }
 
 impl ApiReader<Value> {
     pub fn new(url: &str) -> Self {
-        Self {
-            url: url.into(),
-            _phantom: std::marker::PhantomData,
-        }
+        Self::builder(url)
+            .build()
+            .expect("HTTP client creation failed")
+    }
+
+    pub fn builder(url: &str) -> ApiReaderBuilder {
+        ApiReaderBuilder::new(url)
     }
 
     pub fn with_type<T>(self) -> ApiReader<T> {
         ApiReader {
+            client: self.client,
             url: self.url,
+            method: self.method,
+            headers: self.headers,
+            query: self.query,
+            body: self.body,
+            auth: self.auth,
+            records_path: self.records_path,
+            split_records: self.split_records,
+            pagination: self.pagination,
+            max_pages: self.max_pages,
+            watermark: self.watermark,
+            watermark_value: self.watermark_value,
+            pending: self.pending,
+            change_detection: self.change_detection,
+            last_response: self.last_response,
+            rate_limiter: self.rate_limiter,
+            max_retries: self.max_retries,
+            format: self.format,
+            envelope: self.envelope,
             _phantom: std::marker::PhantomData,
         }
     }
 }
 
-#[async_trait]
-impl<T> Reader for ApiReader<T>
-where
-    T: Serialize + for<'de> Deserialize<'de> + Send + Sync + Debug,
-{
-    type Item = T;
+/// Builds an [`ApiReader`]. Header and query parameter values are
+/// [`Template`]s, rendered again on every request.
+pub struct ApiReaderBuilder {
+    url: String,
+    method: Method,
+    headers: Vec<(String, String)>,
+    query: Vec<(String, String)>,
+    body: Option<Value>,
+    auth: Option<HttpAuth>,
+    records_path: Option<String>,
+    split_records: bool,
+    pagination: Option<Pagination>,
+    max_pages: usize,
+    watermark: Option<Watermark>,
+    change_detection: Option<ChangeDetection>,
+    rate_limit: Option<RateLimit>,
+    max_retries: u32,
+    format: Format,
+    envelope: Option<Envelope>,
+    connect_timeout: Option<Duration>,
+    read_timeout: Option<Duration>,
+    timeout: Option<Duration>,
+    proxy: Option<String>,
+}
+
+impl ApiReaderBuilder {
+    fn new(url: &str) -> Self {
+        Self {
+            url: url.into(),
+            method: Method::GET,
+            headers: Vec::new(),
+            query: Vec::new(),
+            body: None,
+            auth: None,
+            records_path: None,
+            split_records: false,
+            pagination: None,
+            max_pages: DEFAULT_MAX_PAGES,
+            watermark: None,
+            change_detection: None,
+            rate_limit: None,
+            max_retries: DEFAULT_MAX_RETRIES,
+            format: Format::Json,
+            envelope: None,
+            connect_timeout: None,
+            read_timeout: None,
+            timeout: None,
+            proxy: None,
+        }
+    }
+
+    pub fn with_method(mut self, method: Method) -> Self {
+        self.method = method;
+        self
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn with_query(mut self, name: &str, value: &str) -> Self {
+        self.query.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn with_json_body(mut self, body: Value) -> Self {
+        self.body = Some(body);
+        self
+    }
+
+    pub fn with_auth(mut self, auth: HttpAuth) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// Dotted path to the array of records in each response, e.g. `data.items`.
+    pub fn with_records_path(mut self, path: &str) -> Self {
+        self.records_path = Some(path.into());
+        self
+    }
+
+    /// Makes `read_batch` return one item per record instead of a single item
+    /// holding the whole array.
+    pub fn with_split_records(mut self) -> Self {
+        self.split_records = true;
+        self
+    }
+
+    pub fn with_pagination(mut self, pagination: Pagination) -> Self {
+        self.pagination = Some(pagination);
+        self
+    }
+
+    /// Safety limit on the pages read per tick. Defaults to 100.
+    pub fn with_max_pages(mut self, max_pages: usize) -> Self {
+        self.max_pages = max_pages;
+        self
+    }
+
+    /// Polls incrementally. The watermark is available to header and query
+    /// templates as `{watermark}`.
+    pub fn with_watermark(mut self, watermark: Watermark) -> Self {
+        self.watermark = Some(watermark);
+        self
+    }
+
+    pub fn with_change_detection(mut self, change_detection: ChangeDetection) -> Self {
+        self.change_detection = Some(change_detection);
+        self
+    }
+
+    /// Sends at most `requests_per_sec` requests per second, allowing bursts
+    /// of up to `burst` requests.
+    pub fn with_rate_limit(mut self, requests_per_sec: f64, burst: u32) -> Self {
+        self.rate_limit = Some(RateLimit {
+            requests_per_sec,
+            burst,
+            shared: false,
+        });
+        self
+    }
+
+    /// Like [`with_rate_limit`], but the limit is shared with every other
+    /// reader calling the same host. The first reader built for a host sets
+    /// the limit.
+    ///
+    /// [`with_rate_limit`]: ApiReaderBuilder::with_rate_limit
+    pub fn with_shared_rate_limit(mut self, requests_per_sec: f64, burst: u32) -> Self {
+        self.rate_limit = Some(RateLimit {
+            requests_per_sec,
+            burst,
+            shared: true,
+        });
+        self
+    }
+
+    /// Retries of a request answered with `429 Too Many Requests` or a `5xx`
+    /// status. Defaults to 3.
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// How response bodies are parsed. Defaults to JSON.
+    pub fn with_format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn with_envelope(mut self, envelope: Envelope) -> Self {
+        self.envelope = Some(envelope);
+        self
+    }
+
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Maximum time to wait between reads of the response.
+    pub fn with_read_timeout(mut self, timeout: Duration) -> Self {
+        self.read_timeout = Some(timeout);
+        self
+    }
+
+    /// Maximum time for the whole request, from connecting to reading the
+    /// end of the body.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_proxy(mut self, proxy_url: &str) -> Self {
+        self.proxy = Some(proxy_url.into());
+        self
+    }
+
+    pub fn build(self) -> Result<ApiReader<Value>> {
+        if let Some(auth) = &self.auth {
+            auth.validate()
+                .with_context(|| format!("Invalid authentication for {}", self.url))?;
+        }
+
+        let mut client = Client::builder();
+        if let Some(timeout) = self.connect_timeout {
+            client = client.connect_timeout(timeout);
+        }
+        if let Some(timeout) = self.read_timeout {
+            client = client.read_timeout(timeout);
+        }
+        if let Some(timeout) = self.timeout {
+            client = client.timeout(timeout);
+        }
+        if let Some(proxy) = &self.proxy {
+            let proxy =
+                Proxy::all(proxy).with_context(|| format!("Invalid proxy URL '{proxy}'"))?;
+            client = client.proxy(proxy);
+        }
+        let client = client
+            .build()
+            .with_context(|| format!("Failed to create HTTP client for {}", self.url))?;
+
+        let parse_templates = |pairs: Vec<(String, String)>| -> Result<Vec<(String, Template)>> {
+            pairs
+                .into_iter()
+                .map(|(name, value)| Ok((name, Template::parse(&value)?)))
+                .collect()
+        };
+
+        let rate_limiter = match &self.rate_limit {
+            Some(limit) if limit.shared => {
+                let url =
+                    Url::parse(&self.url).with_context(|| format!("Invalid URL '{}'", self.url))?;
+                let host = url
+                    .host_str()
+                    .with_context(|| format!("URL '{}' has no host", self.url))?;
+                RateLimiter::shared(host, limit.requests_per_sec, limit.burst)
+                    .with_context(|| format!("Invalid rate limit for {}", self.url))?
+            }
+            Some(limit) => Arc::new(
+                RateLimiter::new(limit.requests_per_sec, limit.burst)
+                    .with_context(|| format!("Invalid rate limit for {}", self.url))?,
+            ),
+            None => Arc::new(RateLimiter::unlimited()),
+        };
 
-    async fn read(&self) -> Result<Self::Item> {
-        let response = match reqwest::get(&self.url).await {
-            Ok(resp) => resp,
-            Err(e) => {
-                if e.is_timeout() {
-                    log::error!("Request timeout for {}", self.url);
-                } else if e.is_connect() {
-                    log::error!("Connection failed for {}: {}", self.url, e);
-                } else if e.is_request() {
-                    log::error!("Request error for {}: {}", self.url, e);
-                } else {
-                    log::error!("Unknown error for {}: {}", self.url, e);
+        let watermark_value = match &self.watermark {
+            Some(watermark) => {
+                let (value, resumed) = watermark.load()?;
+                if let Some(value) = value.as_ref().filter(|_| resumed) {
+                    log::info!("Resuming {} from watermark {value}", self.url);
                 }
-                return Err(e.into());
+                value
             }
+            None => None,
         };
-        log::trace!(
-            "Response received from {} with status: {}",
-            self.url,
-            response.status()
-        );
+
+        Ok(ApiReader {
+            client,
+            url: self.url,
+            method: self.method,
+            headers: parse_templates(self.headers)?,
+            query: parse_templates(self.query)?,
+            body: self.body,
+            auth: self.auth,
+            records_path: self.records_path,
+            split_records: self.split_records,
+            pagination: self.pagination,
+            max_pages: self.max_pages,
+            watermark: self.watermark,
+            watermark_value: Mutex::new(watermark_value),
+            pending: Mutex::new(PendingRead::default()),
+            change_detection: self.change_detection,
+            last_response: Mutex::new(LastResponse::default()),
+            rate_limiter,
+            max_retries: self.max_retries,
+            format: self.format,
+            envelope: self.envelope,
+            _phantom: std::marker::PhantomData,
+        })
+    }
+}
+
+impl<T> ApiReader<T> {
+    async fn request(
+        &self,
+        url: &str,
+        page_query: Option<(&str, String)>,
+        values: &Value,
+    ) -> Result<reqwest::RequestBuilder> {
+        let mut request = self.client.request(self.method.clone(), url);
+
+        for (name, value) in &self.headers {
+            request = request.header(name, value.render(values));
+        }
+        // Next-page URLs taken from a response already carry their query.
+        if url == self.url && !self.query.is_empty() {
+            let query: Vec<(&str, String)> = self
+                .query
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.render(values)))
+                .collect();
+            request = request.query(&query);
+        }
+        if url == self.url
+            && let Some(param) = self.watermark.as_ref().and_then(Watermark::param)
+            && let Some(watermark) = json_path(values, "watermark").and_then(value_as_string)
+        {
+            request = request.query(&[(param, watermark)]);
+        }
+        if let Some(page_query) = page_query {
+            request = request.query(&[page_query]);
+        }
+        if self.sends_conditional_requests() {
+            let last_response = self.last_response.lock().unwrap();
+            if let Some(etag) = &last_response.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &last_response.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+        if let Some(body) = &self.body {
+            request = request.json(body);
+        }
+        if let Some(auth) = &self.auth {
+            request = auth.apply(&self.client, request).await?;
+        }
+
+        Ok(request)
+    }
+
+    fn sends_conditional_requests(&self) -> bool {
+        self.change_detection.is_some() && self.pagination.is_none()
+    }
+
+    /// Sends one request, waiting for the rate limiter and retrying while the
+    /// server is throttling or failing.
+    async fn send(
+        &self,
+        url: &str,
+        page_query: Option<(&str, String)>,
+        values: &Value,
+    ) -> Result<reqwest::Response> {
+        http::send(
+            url,
+            self.max_retries,
+            Some(&self.rate_limiter),
+            self.auth.as_ref(),
+            || self.request(url, page_query.clone(), values),
+        )
+        .await
+    }
+
+    /// Sends one request and parses the response body in the reader's format.
+    /// Returns `None` when the server answers `304 Not Modified`.
+    async fn fetch(
+        &self,
+        url: &str,
+        page_query: Option<(&str, String)>,
+        values: &Value,
+    ) -> Result<Option<Page>> {
+        let response = self.send(url, page_query, values).await?;
+
+        if response.status() == StatusCode::NOT_MODIFIED {
+            log::debug!("{url} was not modified since the last poll");
+            return Ok(None);
+        }
 
         if !response.status().is_success() {
-            log::error!("HTTP error {}: {}", response.status(), self.url);
+            log::error!("HTTP error {}: {url}", response.status());
             return Err(anyhow!("HTTP error: {}", response.status()));
         }
 
-        let data: T = response.json().await.map_err(|e| {
-            log::error!("Failed to parse JSON from {}: {}", self.url, e);
+        if self.sends_conditional_requests() {
+            let header = |name| {
+                response
+                    .headers()
+                    .get(name)
+                    .and_then(|value| value.to_str().ok())
+                    .map(String::from)
+            };
+            let mut pending = self.pending.lock().unwrap();
+            let last_response = pending.response.get_or_insert_with(LastResponse::default);
+            last_response.etag = header(ETAG);
+            last_response.last_modified = header(LAST_MODIFIED);
+        }
+
+        let next_link = next_link(response.headers());
+        let meta = ResponseMeta {
+            url: response.url().clone(),
+            status: response.status().as_u16(),
+            headers: match &self.envelope {
+                Some(envelope) => envelope.select_headers(response.headers()),
+                None => Map::new(),
+            },
+            fetched_at: Utc::now(),
+        };
+        let text = response.text().await?;
+        let body = self.format.parse(&text).map_err(|e| {
+            log::error!("Failed to parse {} from {url}: {e}", self.format);
             e
         })?;
-        log::debug!("Successfully retrieved data from: {}", self.url);
+        log::debug!("Successfully retrieved data from: {url}");
+
+        Ok(Some(Page {
+            body,
+            next_link,
+            meta,
+        }))
+    }
+
+    /// Returns the records of a page: the array at `records_path`, or the
+    /// whole body when no path is set.
+    fn records(&self, page: Value) -> Result<Vec<Value>> {
+        let records = match &self.records_path {
+            Some(path) => json_path(&page, path).cloned().with_context(|| {
+                format!("No records found at '{path}' in response from {}", self.url)
+            })?,
+            None => page,
+        };
+        match records {
+            Value::Array(records) => Ok(records),
+            _ => Err(anyhow!(
+                "Expected an array of records in response from {}",
+                self.url
+            )),
+        }
+    }
+
+    /// Fetches the response, walking every page when the reader is paginated.
+    /// Paginated responses, and responses read with a records path, are
+    /// reduced to a single array of records. Returns `None` when the server
+    /// reports that the response was not modified.
+    async fn fetch_all(&self, values: &Value) -> Result<Option<(Value, ResponseMeta)>> {
+        let Some(pagination) = &self.pagination else {
+            let Some(page) = self.fetch(&self.url, None, values).await? else {
+                return Ok(None);
+            };
+            let data = match &self.records_path {
+                Some(_) => Value::Array(self.records(page.body)?),
+                None => page.body,
+            };
+            return Ok(Some((data, page.meta)));
+        };
+
+        let mut records = Vec::new();
+        let mut page = self
+            .fetch(&self.url, pagination.first_page_query(), values)
+            .await?
+            .context("Unexpected 304 Not Modified for a paginated request")?;
+        let meta = page.meta.clone();
+        let mut pages = 1;
+        let mut number = pagination.start();
+        let mut previous_cursor = None;
+
+        loop {
+            let body = page.body;
+            let next = match pagination {
+                Pagination::Cursor { cursor_path, .. } => json_path(&body, cursor_path)
+                    .and_then(value_as_string)
+                    .filter(|cursor| previous_cursor.as_ref() != Some(cursor)),
+                Pagination::NextUrl { path } => json_path(&body, path).and_then(value_as_string),
+                _ => None,
+            };
+
+            let page_records = self.records(body)?;
+            let page_len = page_records.len() as u64;
+            records.extend(page_records);
+            log::debug!(
+                "Read page {pages} from {} with {page_len} record(s)",
+                self.url
+            );
+
+            let request = match pagination {
+                Pagination::PageNumber { param, .. } if page_len > 0 => {
+                    number += 1;
+                    Some((self.url.clone(), Some((param.as_str(), number.to_string()))))
+                }
+                Pagination::Offset { param, .. } if page_len > 0 => {
+                    number += page_len;
+                    Some((self.url.clone(), Some((param.as_str(), number.to_string()))))
+                }
+                Pagination::Cursor { param, .. } => {
+                    previous_cursor = next.clone();
+                    next.map(|cursor| (self.url.clone(), Some((param.as_str(), cursor))))
+                }
+                Pagination::LinkHeader => page
+                    .next_link
+                    .and_then(|link| page.meta.url.join(&link).ok())
+                    .map(|url| (url.to_string(), None)),
+                Pagination::NextUrl { .. } => next
+                    .and_then(|link| page.meta.url.join(&link).ok())
+                    .map(|url| (url.to_string(), None)),
+                _ => None,
+            };
+
+            let Some((url, page_query)) = request else {
+                break;
+            };
+            if pages >= self.max_pages {
+                log::warn!(
+                    "Stopped reading {} after reaching the limit of {} pages",
+                    self.url,
+                    self.max_pages
+                );
+                break;
+            }
+
+            page = self
+                .fetch(&url, page_query, values)
+                .await?
+                .context("Unexpected 304 Not Modified for a paginated request")?;
+            pages += 1;
+        }
+
+        log::debug!(
+            "Read {} record(s) from {} in {pages} page(s)",
+            records.len(),
+            self.url
+        );
+        Ok(Some((Value::Array(records), meta)))
+    }
+
+    /// Reads everything new since the last poll, keeping the watermark and
+    /// response validators it reaches until [`commit`]. Returns `None` when
+    /// change detection finds nothing new.
+    ///
+    /// [`commit`]: Reader::commit
+    async fn poll(&self) -> Result<Option<(Value, ResponseMeta)>> {
+        *self.pending.lock().unwrap() = PendingRead::default();
+        let current = self.watermark_value.lock().unwrap().clone();
+        let values = match &current {
+            Some(watermark) => json!({ "watermark": watermark }),
+            None => json!({}),
+        };
+        let Some((data, meta)) = self.fetch_all(&values).await? else {
+            return Ok(None);
+        };
+
+        if let Some(change_detection) = &self.change_detection {
+            let hash = change_detection.hash(&data);
+            self.pending
+                .lock()
+                .unwrap()
+                .response
+                .get_or_insert_with(LastResponse::default)
+                .hash = Some(hash);
+            if self.last_response.lock().unwrap().hash == Some(hash) {
+                log::debug!("Response from {} has not changed", self.url);
+                return Ok(None);
+            }
+        }
+
+        let Some(watermark) = &self.watermark else {
+            return Ok(Some((data, meta)));
+        };
+        let records = match &data {
+            Value::Array(records) => records.as_slice(),
+            record => std::slice::from_ref(record),
+        };
+        self.pending.lock().unwrap().watermark = watermark.next(current.as_ref(), records);
+
+        Ok(Some((data, meta)))
+    }
+
+    fn wrap(&self, body: Value, meta: &ResponseMeta) -> Value {
+        match &self.envelope {
+            Some(envelope) => envelope.wrap(body, meta),
+            None => body,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct RateLimit {
+    requests_per_sec: f64,
+    burst: u32,
+    shared: bool,
+}
+
+struct Page {
+    body: Value,
+    next_link: Option<String>,
+    meta: ResponseMeta,
+}
+
+#[derive(Debug, Clone)]
+struct ResponseMeta {
+    url: Url,
+    status: u16,
+    headers: Map<String, Value>,
+    fetched_at: DateTime<Utc>,
+}
+
+fn value_as_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) if !s.is_empty() => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// Extracts the `rel="next"` target of a `Link` header (RFC 5988).
+fn next_link(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get_all(LINK)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .find_map(|link| {
+            let mut parts = link.split(';');
+            let target = parts.next()?.trim();
+            let is_next = parts.any(|param| {
+                let param = param.trim().replace(' ', "");
+                param == "rel=\"next\"" || param == "rel=next"
+            });
+            is_next.then(|| {
+                target
+                    .trim_start_matches('<')
+                    .trim_end_matches('>')
+                    .to_string()
+            })
+        })
+}
+
+#[async_trait]
+impl<T> Reader for ApiReader<T>
+where
+    T: Serialize + for<'de> Deserialize<'de> + Send + Sync + Debug,
+{
+    type Item = T;
+
+    async fn read(&self) -> Result<Option<Self::Item>> {
+        let Some((data, meta)) = self.poll().await? else {
+            return Ok(None);
+        };
+        let data: T = serde_json::from_value(self.wrap(data, &meta))
+            .with_context(|| format!("Failed to deserialize response from {}", self.url))?;
         log::trace!("Received payload: {data:?}");
 
-        Ok(data)
+        Ok(Some(data))
+    }
+
+    async fn read_batch(&self) -> Result<Vec<Self::Item>> {
+        let Some((data, meta)) = self.poll().await? else {
+            return Ok(Vec::new());
+        };
+        let records = match data {
+            Value::Array(records) if self.split_records => records,
+            data => vec![data],
+        };
+
+        records
+            .into_iter()
+            .map(|record| {
+                let record: T = serde_json::from_value(self.wrap(record, &meta))
+                    .with_context(|| format!("Failed to deserialize record from {}", self.url))?;
+                log::trace!("Received record: {record:?}");
+                Ok(record)
+            })
+            .collect()
+    }
+
+    /// Saves and moves to the watermark of the last read, and remembers its
+    /// response for change detection.
+    async fn commit(&self) -> Result<()> {
+        let pending = std::mem::take(&mut *self.pending.lock().unwrap());
+        if let (Some(watermark), Some(latest)) = (&self.watermark, pending.watermark) {
+            watermark.save(&latest)?;
+            log::debug!("Watermark for {} advanced to {latest}", self.url);
+            *self.watermark_value.lock().unwrap() = Some(latest);
+        }
+        if let Some(response) = pending.response {
+            *self.last_response.lock().unwrap() = response;
+        }
+        Ok(())
     }
 }
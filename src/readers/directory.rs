@@ -0,0 +1,352 @@
+use std::collections::BTreeSet;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime};
+
+use anyhow::{Context, Result, bail};
+use async_stream::stream;
+use chrono::Utc;
+use glob::{MatchOptions, Pattern};
+use serde::{Deserialize, Serialize};
+use tokio::time::sleep;
+use tokio_stream::Stream;
+
+use crate::format::Format;
+use crate::readers::StreamReader;
+use crate::schemas::Json;
+use crate::state::StateFile;
+
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+const DEFAULT_SETTLE_TIME: Duration = Duration::from_secs(1);
+
+/// Files whose records were all written but which were not moved out of the
+/// directory yet, so a restart does not ingest them again.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct DirectoryState {
+    completed: BTreeSet<String>,
+}
+
+/// Ingests files dropped into a directory. Each file matching the pattern is
+/// parsed, its records are emitted, and it is moved to the processed
+/// directory once they have all been written, or to the failed directory when
+/// it cannot be parsed. Files are taken oldest first, once they have not been
+/// modified for the settle time.
+///
+/// A file either has all of its records emitted or none: a file with an
+/// invalid record is moved to the failed directory as a whole. When a record
+/// fails to be written, the file is left in place and ingested again on a
+/// later scan, so the records before it are emitted again.
+pub struct DirectoryReader<T: Json> {
+    dir: PathBuf,
+    pattern: Pattern,
+    format: Option<Format>,
+    processed_dir: PathBuf,
+    failed_dir: PathBuf,
+    state_file: Option<StateFile>,
+    poll_interval: Duration,
+    settle_time: Duration,
+    /// Set once the record yielded last has been written.
+    acknowledged: AtomicBool,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Json> DirectoryReader<T> {
+    pub fn builder(dir: impl Into<PathBuf>) -> DirectoryReaderBuilder<T> {
+        DirectoryReaderBuilder::new(dir)
+    }
+}
+
+/// Builds a [`DirectoryReader`]. By default every file is ingested, its
+/// format is guessed from its extension, and files are moved to `processed/`
+/// and `failed/` inside the watched directory.
+pub struct DirectoryReaderBuilder<T: Json> {
+    dir: PathBuf,
+    pattern: String,
+    format: Option<Format>,
+    processed_dir: Option<PathBuf>,
+    failed_dir: Option<PathBuf>,
+    state_file: Option<StateFile>,
+    poll_interval: Duration,
+    settle_time: Duration,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Json> DirectoryReaderBuilder<T> {
+    fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            pattern: "*".into(),
+            format: None,
+            processed_dir: None,
+            failed_dir: None,
+            state_file: None,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            settle_time: DEFAULT_SETTLE_TIME,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    /// Glob matched against file names, e.g. `orders-*.csv`. Hidden files
+    /// only match patterns starting with a dot.
+    pub fn with_pattern(mut self, pattern: &str) -> Self {
+        self.pattern = pattern.into();
+        self
+    }
+
+    /// Parses every file with this format instead of guessing it from the
+    /// extension.
+    pub fn with_format(mut self, format: Format) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    pub fn with_processed_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.processed_dir = Some(dir.into());
+        self
+    }
+
+    pub fn with_failed_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.failed_dir = Some(dir.into());
+        self
+    }
+
+    /// Keeps track of ingested files in this file, so that a file is not
+    /// ingested again if the reader stops before moving it.
+    pub fn with_state_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.state_file = Some(StateFile::new(path));
+        self
+    }
+
+    /// How often the directory is scanned for new files. Defaults to 1s.
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// How long a file must go unmodified before it is ingested, so that
+    /// files still being written are left alone. Defaults to 1s.
+    pub fn with_settle_time(mut self, settle_time: Duration) -> Self {
+        self.settle_time = settle_time;
+        self
+    }
+
+    pub fn build(self) -> Result<DirectoryReader<T>> {
+        let pattern = Pattern::new(&self.pattern)
+            .with_context(|| format!("Invalid file pattern '{}'", self.pattern))?;
+        if !self.dir.is_dir() {
+            bail!("'{}' is not a directory", self.dir.display());
+        }
+
+        Ok(DirectoryReader {
+            processed_dir: self
+                .processed_dir
+                .unwrap_or_else(|| self.dir.join("processed")),
+            failed_dir: self.failed_dir.unwrap_or_else(|| self.dir.join("failed")),
+            dir: self.dir,
+            pattern,
+            format: self.format,
+            state_file: self.state_file,
+            poll_interval: self.poll_interval,
+            settle_time: self.settle_time,
+            acknowledged: AtomicBool::new(false),
+            _marker: std::marker::PhantomData,
+        })
+    }
+}
+
+impl<T: Json> DirectoryReader<T> {
+    /// Files ready to be ingested, oldest first.
+    async fn scan(&self) -> Result<Vec<(PathBuf, String)>> {
+        let options = MatchOptions {
+            require_literal_leading_dot: true,
+            ..MatchOptions::new()
+        };
+        let mut entries = tokio::fs::read_dir(&self.dir)
+            .await
+            .with_context(|| format!("Failed to list '{}'", self.dir.display()))?;
+
+        let mut files = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let Ok(name) = entry.file_name().into_string() else {
+                continue;
+            };
+            if !self.pattern.matches_with(&name, options) {
+                continue;
+            }
+            let metadata = entry.metadata().await?;
+            if !metadata.is_file() {
+                continue;
+            }
+            let modified = metadata.modified()?;
+            let age = SystemTime::now()
+                .duration_since(modified)
+                .unwrap_or_default();
+            if age < self.settle_time {
+                log::trace!("Waiting for '{name}' to settle");
+                continue;
+            }
+            files.push((modified, name, entry.path()));
+        }
+
+        files.sort();
+        Ok(files
+            .into_iter()
+            .map(|(_, name, path)| (path, name))
+            .collect())
+    }
+
+    async fn parse(&self, path: &Path) -> Result<Vec<T>> {
+        let format = match self.format {
+            Some(format) => format,
+            None => Format::from_extension(path)
+                .with_context(|| format!("Unknown format of '{}'", path.display()))?,
+        };
+        let text = tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("Failed to read '{}'", path.display()))?;
+
+        format
+            .parse_records(&text)?
+            .into_iter()
+            .enumerate()
+            .map(|(i, record)| {
+                serde_json::from_value(record)
+                    .with_context(|| format!("Failed to deserialize record {}", i + 1))
+            })
+            .collect()
+    }
+
+    fn load_state(&self) -> Result<DirectoryState> {
+        match &self.state_file {
+            Some(state_file) => Ok(state_file.load()?.unwrap_or_default()),
+            None => Ok(DirectoryState::default()),
+        }
+    }
+
+    fn save_state(&self, state: &DirectoryState) {
+        if let Some(state_file) = &self.state_file
+            && let Err(e) = state_file.save(state)
+        {
+            log::error!("{e:#}");
+        }
+    }
+}
+
+/// Moves `path` into `dir`, adding a timestamp to its name if a file with the
+/// same name is already there.
+async fn move_to(path: &Path, name: &str, dir: &Path) -> Result<PathBuf> {
+    tokio::fs::create_dir_all(dir)
+        .await
+        .with_context(|| format!("Failed to create directory '{}'", dir.display()))?;
+
+    let mut target = dir.join(name);
+    if tokio::fs::try_exists(&target).await? {
+        let stem = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy())
+            .unwrap_or_default();
+        let extension = path
+            .extension()
+            .map(|extension| format!(".{}", extension.to_string_lossy()))
+            .unwrap_or_default();
+        let timestamp = Utc::now().format("%Y%m%dT%H%M%S%.3f");
+        target = dir.join(format!("{stem}-{timestamp}{extension}"));
+    }
+
+    match tokio::fs::rename(path, &target).await {
+        Ok(()) => {}
+        // The target directory is on another filesystem.
+        Err(e) if e.kind() == ErrorKind::CrossesDevices => {
+            tokio::fs::copy(path, &target).await?;
+            tokio::fs::remove_file(path).await?;
+        }
+        Err(e) => {
+            return Err(e).with_context(|| {
+                format!("Failed to move '{}' to '{}'", path.display(), dir.display())
+            });
+        }
+    }
+    Ok(target)
+}
+
+impl<T: Json> StreamReader for DirectoryReader<T> {
+    type Item = T;
+
+    fn is_bounded(&self) -> bool {
+        false
+    }
+
+    async fn stream(&self) -> impl Stream<Item = Self::Item> {
+        stream! {
+            let mut state = match self.load_state() {
+                Ok(state) => state,
+                Err(e) => {
+                    log::error!("{e:#}");
+                    return;
+                }
+            };
+            log::info!("Watching '{}' for '{}'", self.dir.display(), self.pattern);
+
+            loop {
+                let files = self.scan().await.unwrap_or_else(|e| {
+                    log::error!("{e:#}");
+                    Vec::new()
+                });
+
+                for (path, name) in files {
+                    if !state.completed.contains(&name) {
+                        let records = match self.parse(&path).await {
+                            Ok(records) => records,
+                            Err(e) => {
+                                log::error!("Failed to ingest '{}': {e:#}", path.display());
+                                match move_to(&path, &name, &self.failed_dir).await {
+                                    Ok(target) => log::info!("Moved '{name}' to '{}'", target.display()),
+                                    Err(e) => log::error!("{e:#}"),
+                                }
+                                continue;
+                            }
+                        };
+
+                        let count = records.len();
+                        let mut written = 0;
+                        for record in records {
+                            self.acknowledged.store(false, Ordering::Relaxed);
+                            yield record;
+                            if !self.acknowledged.load(Ordering::Relaxed) {
+                                break;
+                            }
+                            written += 1;
+                        }
+                        if written < count {
+                            log::warn!(
+                                "Only {written} of {count} record(s) from '{}' were written, leaving it in place",
+                                path.display()
+                            );
+                            break;
+                        }
+                        log::info!("Ingested {count} record(s) from '{}'", path.display());
+                        state.completed.insert(name.clone());
+                        self.save_state(&state);
+                    }
+
+                    match move_to(&path, &name, &self.processed_dir).await {
+                        Ok(target) => {
+                            log::debug!("Moved '{name}' to '{}'", target.display());
+                            state.completed.remove(&name);
+                            self.save_state(&state);
+                        }
+                        Err(e) => log::error!("{e:#}"),
+                    }
+                }
+
+                sleep(self.poll_interval).await;
+            }
+        }
+    }
+
+    async fn acknowledge(&self) -> Result<()> {
+        self.acknowledged.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+}
@@ -0,0 +1,346 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use async_stream::stream;
+use async_trait::async_trait;
+use serde_json::{Map, Value};
+use tokio::fs::File;
+use tokio::io::{AsyncBufReadExt, AsyncSeekExt, BufReader, SeekFrom};
+use tokio::time::sleep;
+use tokio_stream::Stream;
+
+use crate::format::Format;
+use crate::readers::{Reader, StreamReader};
+use crate::schemas::Json;
+
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Reads records from a file: one per line for NDJSON and text, one per row
+/// for CSV, one per element of a JSON array, or the whole document for other
+/// JSON and XML.
+///
+/// As a [`Reader`] the whole file is read on every call. As a
+/// [`StreamReader`] the file is read once to the end, or with
+/// [`with_follow`] tailed like `tail -F`: new lines are emitted as they are
+/// appended, and the file is reopened from the start when it is truncated or
+/// replaced by a new one. Tailing reads line by line, so CSV fields spanning
+/// several lines are not supported there.
+///
+/// [`with_follow`]: FileReaderBuilder::with_follow
+pub struct FileReader<T: Json> {
+    path: PathBuf,
+    format: Format,
+    follow: bool,
+    from_end: bool,
+    poll_interval: Duration,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Json> FileReader<T> {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self::builder(path).build().expect("Invalid file reader")
+    }
+
+    pub fn builder(path: impl Into<PathBuf>) -> FileReaderBuilder<T> {
+        FileReaderBuilder::new(path)
+    }
+}
+
+/// Builds a [`FileReader`]. Files are read as NDJSON unless another format
+/// is set.
+pub struct FileReaderBuilder<T: Json> {
+    path: PathBuf,
+    format: Format,
+    follow: bool,
+    from_end: bool,
+    poll_interval: Duration,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Json> FileReaderBuilder<T> {
+    fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            format: Format::Ndjson,
+            follow: false,
+            from_end: false,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    pub fn with_format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Keeps the stream open and emits lines as they are appended.
+    pub fn with_follow(mut self) -> Self {
+        self.follow = true;
+        self
+    }
+
+    /// Only emits lines appended after the stream starts. Implies
+    /// [`with_follow`](FileReaderBuilder::with_follow).
+    pub fn with_start_at_end(mut self) -> Self {
+        self.follow = true;
+        self.from_end = true;
+        self
+    }
+
+    /// How often a followed file is checked for new data. Defaults to 500ms.
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    pub fn build(self) -> Result<FileReader<T>> {
+        if self.follow && matches!(self.format, Format::Json | Format::Xml) {
+            bail!(
+                "Cannot follow '{}' as {}, only line-based formats can be tailed",
+                self.path.display(),
+                self.format
+            );
+        }
+
+        Ok(FileReader {
+            path: self.path,
+            format: self.format,
+            follow: self.follow,
+            from_end: self.from_end,
+            poll_interval: self.poll_interval,
+            _marker: std::marker::PhantomData,
+        })
+    }
+}
+
+/// Turns lines into records. CSV files take their column names from the
+/// first line.
+struct LineParser {
+    format: Format,
+    csv_headers: Option<csv::StringRecord>,
+}
+
+impl LineParser {
+    fn new(format: Format) -> Self {
+        Self {
+            format,
+            csv_headers: None,
+        }
+    }
+
+    fn parse(&mut self, line: &str) -> Result<Option<Value>> {
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.trim().is_empty() {
+            return Ok(None);
+        }
+
+        match self.format {
+            Format::Text => Ok(Some(Value::String(line.into()))),
+            Format::Csv => {
+                let mut reader = csv::ReaderBuilder::new()
+                    .has_headers(false)
+                    .from_reader(line.as_bytes());
+                let record = reader
+                    .records()
+                    .next()
+                    .transpose()?
+                    .context("Empty CSV line")?;
+                let Some(headers) = &self.csv_headers else {
+                    self.csv_headers = Some(record);
+                    return Ok(None);
+                };
+                let row: Map<String, Value> = headers
+                    .iter()
+                    .zip(record.iter())
+                    .map(|(name, value)| (name.to_string(), Value::String(value.into())))
+                    .collect();
+                Ok(Some(Value::Object(row)))
+            }
+            _ => Ok(Some(serde_json::from_str(line)?)),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn file_id(metadata: &std::fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.ino())
+}
+
+#[cfg(not(unix))]
+fn file_id(_metadata: &std::fs::Metadata) -> Option<u64> {
+    None
+}
+
+impl<T: Json> FileReader<T> {
+    /// Reads the whole file into records.
+    async fn read_records(&self) -> Result<Vec<Value>> {
+        let text = tokio::fs::read_to_string(&self.path)
+            .await
+            .with_context(|| format!("Failed to read '{}'", self.path.display()))?;
+
+        let records = self.format.parse_records(&text)?;
+        log::debug!(
+            "Read {} record(s) from '{}'",
+            records.len(),
+            self.path.display()
+        );
+        Ok(records)
+    }
+
+    /// Opens the file, skipping to its end if `from_end`. The CSV header is
+    /// still read into `parser` first, since rows are named after it.
+    async fn open(
+        &self,
+        from_end: bool,
+        parser: &mut LineParser,
+    ) -> Result<(BufReader<File>, Option<u64>)> {
+        let file = File::open(&self.path)
+            .await
+            .with_context(|| format!("Failed to open '{}'", self.path.display()))?;
+        let id = file_id(&file.metadata().await?);
+        let mut reader = BufReader::new(file);
+        if from_end {
+            let mut header = String::new();
+            if self.format == Format::Csv {
+                reader.read_line(&mut header).await?;
+            }
+            // Until the header is complete, there are no rows to skip.
+            if self.format == Format::Csv && !header.ends_with('\n') {
+                reader.seek(SeekFrom::Start(0)).await?;
+            } else {
+                parser.parse(&header)?;
+                reader.seek(SeekFrom::End(0)).await?;
+            }
+        }
+        Ok((reader, id))
+    }
+
+    /// Whether the file at `path` is no longer the one being read, because it
+    /// was truncated or replaced.
+    async fn rotated(&self, id: Option<u64>, position: u64) -> bool {
+        match tokio::fs::metadata(&self.path).await {
+            Ok(metadata) => metadata.len() < position || file_id(&metadata) != id,
+            // Between the old file being moved and the new one created.
+            Err(_) => false,
+        }
+    }
+}
+
+#[async_trait]
+impl<T: Json> Reader for FileReader<T> {
+    type Item = T;
+
+    async fn read(&self) -> Result<Option<Self::Item>> {
+        let text = tokio::fs::read_to_string(&self.path)
+            .await
+            .with_context(|| format!("Failed to read '{}'", self.path.display()))?;
+        let data = serde_json::from_value(self.format.parse(&text)?)
+            .with_context(|| format!("Failed to deserialize '{}'", self.path.display()))?;
+        log::trace!("Read payload: {data:?}");
+        Ok(Some(data))
+    }
+
+    async fn read_batch(&self) -> Result<Vec<Self::Item>> {
+        self.read_records()
+            .await?
+            .into_iter()
+            .map(|record| {
+                serde_json::from_value(record).with_context(|| {
+                    format!(
+                        "Failed to deserialize record from '{}'",
+                        self.path.display()
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+impl<T: Json> StreamReader for FileReader<T> {
+    type Item = T;
+
+    fn is_bounded(&self) -> bool {
+        !self.follow
+    }
+
+    async fn stream(&self) -> impl Stream<Item = Self::Item> {
+        stream! {
+            if !self.follow {
+                let records = match self.read_batch().await {
+                    Ok(records) => records,
+                    Err(e) => {
+                        log::error!("{e:#}");
+                        return;
+                    }
+                };
+                for record in records {
+                    yield record;
+                }
+                return;
+            }
+
+            let mut parser = LineParser::new(self.format);
+            let (mut reader, mut id) = match self.open(self.from_end, &mut parser).await {
+                Ok(opened) => opened,
+                Err(e) => {
+                    log::error!("{e:#}");
+                    return;
+                }
+            };
+            let mut position = reader.stream_position().await.unwrap_or(0);
+            let mut line = String::new();
+            log::info!("Following '{}'", self.path.display());
+
+            loop {
+                let read = match reader.read_line(&mut line).await {
+                    Ok(read) => read,
+                    Err(e) => {
+                        log::error!("Failed to read '{}': {e}", self.path.display());
+                        return;
+                    }
+                };
+                position += read as u64;
+
+                // A line without its newline is still being written.
+                if read > 0 && line.ends_with('\n') {
+                    let record = parser.parse(&line).and_then(|record| {
+                        record
+                            .map(serde_json::from_value::<T>)
+                            .transpose()
+                            .map_err(Into::into)
+                    });
+                    match record {
+                        Ok(Some(record)) => yield record,
+                        Ok(None) => {}
+                        Err(e) => log::error!(
+                            "Skipping invalid line in '{}': {e:#}",
+                            self.path.display()
+                        ),
+                    }
+                    line.clear();
+                    continue;
+                }
+
+                if self.rotated(id, position).await {
+                    log::info!("'{}' was rotated, reopening", self.path.display());
+                    let mut new_parser = LineParser::new(self.format);
+                    match self.open(false, &mut new_parser).await {
+                        Ok((new_reader, new_id)) => {
+                            reader = new_reader;
+                            parser = new_parser;
+                            id = new_id;
+                            position = 0;
+                            line.clear();
+                            continue;
+                        }
+                        Err(e) => log::warn!("{e:#}"),
+                    }
+                }
+                sleep(self.poll_interval).await;
+            }
+        }
+    }
+}
@@ -0,0 +1,361 @@
+use std::net::{SocketAddr, TcpListener as StdTcpListener};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result, bail};
+use async_stream::stream;
+use axum::Router;
+use axum::body::Bytes;
+use axum::extract::{DefaultBodyLimit, State};
+use axum::http::header::{CONTENT_TYPE, RETRY_AFTER};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::mpsc::{Receiver, Sender, channel};
+use tokio_stream::Stream;
+
+use crate::readers::StreamReader;
+use crate::schemas::Json;
+use crate::secret::Secret;
+
+const DEFAULT_BUFFER: usize = 1000;
+const DEFAULT_MAX_BODY_SIZE: usize = 2 * 1024 * 1024;
+
+/// How an `HttpServerReader` checks that a request comes from a trusted sender.
+#[derive(Debug, Clone)]
+pub enum Verification {
+    /// `header` must hold the secret itself, e.g. `X-Webhook-Token`.
+    SharedSecret { header: String, secret: Secret },
+    /// `header` must hold the hex HMAC-SHA256 of the body keyed with the
+    /// secret, after `prefix` (e.g. `sha256=` for GitHub webhooks).
+    HmacSha256 {
+        header: String,
+        secret: Secret,
+        prefix: String,
+    },
+}
+
+impl Verification {
+    pub fn shared_secret(header: &str, secret: impl Into<Secret>) -> Self {
+        Verification::SharedSecret {
+            header: header.into(),
+            secret: secret.into(),
+        }
+    }
+
+    pub fn hmac_sha256(header: &str, secret: impl Into<Secret>, prefix: &str) -> Self {
+        Verification::HmacSha256 {
+            header: header.into(),
+            secret: secret.into(),
+            prefix: prefix.into(),
+        }
+    }
+
+    fn header(&self) -> &str {
+        match self {
+            Verification::SharedSecret { header, .. } | Verification::HmacSha256 { header, .. } => {
+                header
+            }
+        }
+    }
+
+    fn verify(&self, secret: &[u8], headers: &HeaderMap, body: &[u8]) -> bool {
+        let Some(value) = headers
+            .get(self.header())
+            .and_then(|value| value.to_str().ok())
+        else {
+            return false;
+        };
+
+        match self {
+            Verification::SharedSecret { .. } => constant_time_eq(value.as_bytes(), secret),
+            Verification::HmacSha256 { prefix, .. } => {
+                let Some(signature) = value
+                    .strip_prefix(prefix.as_str())
+                    .and_then(|signature| hex::decode(signature.trim()).ok())
+                else {
+                    return false;
+                };
+                let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret) else {
+                    return false;
+                };
+                mac.update(body);
+                mac.verify_slice(&signature).is_ok()
+            }
+        }
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Receives records pushed over HTTP. Each configured path accepts POSTed
+/// JSON, or NDJSON when sent as `application/x-ndjson`, and answers
+/// `202 Accepted` once every record in the body is queued for the operation.
+/// When the queue is full it answers `503 Service Unavailable` so the sender
+/// retries later.
+pub struct HttpServerReader<T: Json> {
+    addr: SocketAddr,
+    server: Mutex<Option<(StdTcpListener, Router)>>,
+    receiver: Mutex<Option<Receiver<T>>>,
+}
+
+impl<T: Json + 'static> HttpServerReader<T> {
+    pub fn builder(addr: &str) -> HttpServerReaderBuilder<T> {
+        HttpServerReaderBuilder::new(addr)
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+/// Builds an [`HttpServerReader`]. The address is bound by `build`, so a port
+/// already in use is reported along with other configuration errors.
+pub struct HttpServerReaderBuilder<T: Json> {
+    addr: String,
+    paths: Vec<String>,
+    verification: Option<Verification>,
+    buffer: usize,
+    max_body_size: usize,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Json + 'static> HttpServerReaderBuilder<T> {
+    fn new(addr: &str) -> Self {
+        Self {
+            addr: addr.into(),
+            paths: Vec::new(),
+            verification: None,
+            buffer: DEFAULT_BUFFER,
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    /// Accepts records on this path. Defaults to `/` when no path is given.
+    pub fn with_path(mut self, path: &str) -> Self {
+        self.paths.push(path.into());
+        self
+    }
+
+    pub fn with_verification(mut self, verification: Verification) -> Self {
+        self.verification = Some(verification);
+        self
+    }
+
+    /// Records that may wait for the writer before requests are rejected.
+    /// Defaults to 1000.
+    pub fn with_buffer(mut self, buffer: usize) -> Self {
+        self.buffer = buffer;
+        self
+    }
+
+    /// Largest request body accepted, in bytes. Defaults to 2 MiB.
+    pub fn with_max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    pub fn build(self) -> Result<HttpServerReader<T>> {
+        if self.buffer == 0 {
+            bail!("HTTP server buffer must hold at least one record");
+        }
+        let verification = match self.verification {
+            Some(verification) => {
+                let secret = match &verification {
+                    Verification::SharedSecret { secret, .. }
+                    | Verification::HmacSha256 { secret, .. } => secret.resolve()?,
+                };
+                Some((verification, secret.into_bytes()))
+            }
+            None => None,
+        };
+
+        let listener = StdTcpListener::bind(&self.addr)
+            .with_context(|| format!("Failed to bind HTTP server to '{}'", self.addr))?;
+        listener.set_nonblocking(true)?;
+        let addr = listener.local_addr()?;
+
+        let (sender, receiver) = channel(self.buffer);
+        let state = Arc::new(ServerState {
+            sender,
+            verification,
+        });
+
+        let paths = match self.paths.is_empty() {
+            true => vec!["/".to_string()],
+            false => self.paths,
+        };
+        let router = paths
+            .iter()
+            .fold(Router::new(), |router, path| {
+                router.route(path, post(receive::<T>))
+            })
+            .layer(DefaultBodyLimit::max(self.max_body_size))
+            .with_state(state);
+
+        Ok(HttpServerReader {
+            addr,
+            server: Mutex::new(Some((listener, router))),
+            receiver: Mutex::new(Some(receiver)),
+        })
+    }
+}
+
+struct ServerState<T> {
+    sender: Sender<T>,
+    verification: Option<(Verification, Vec<u8>)>,
+}
+
+async fn receive<T: Json>(
+    State(state): State<Arc<ServerState<T>>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    if let Some((verification, secret)) = &state.verification
+        && !verification.verify(secret, &headers, &body)
+    {
+        log::warn!("Rejected request with a missing or invalid signature");
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    let records = match parse_records::<T>(&headers, &body) {
+        Ok(records) => records,
+        Err(e) => {
+            log::warn!("Rejected invalid request body: {e:#}");
+            return (StatusCode::BAD_REQUEST, format!("{e:#}")).into_response();
+        }
+    };
+    if records.is_empty() {
+        return StatusCode::ACCEPTED.into_response();
+    }
+    if records.len() > state.sender.max_capacity() {
+        return (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "Too many records in one request",
+        )
+            .into_response();
+    }
+
+    // Either every record of the body is queued or none is.
+    match state.sender.try_reserve_many(records.len()) {
+        Ok(permits) => {
+            let count = records.len();
+            for (permit, record) in permits.zip(records) {
+                permit.send(record);
+            }
+            log::debug!("Accepted {count} record(s)");
+            StatusCode::ACCEPTED.into_response()
+        }
+        Err(TrySendError::Full(_)) => {
+            log::warn!("Buffer full, asking the sender to retry later");
+            (StatusCode::SERVICE_UNAVAILABLE, [(RETRY_AFTER, "1")]).into_response()
+        }
+        Err(TrySendError::Closed(_)) => StatusCode::SERVICE_UNAVAILABLE.into_response(),
+    }
+}
+
+fn parse_records<T: Json>(headers: &HeaderMap, body: &[u8]) -> Result<Vec<T>> {
+    let body = std::str::from_utf8(body).context("Body is not valid UTF-8")?;
+    let is_ndjson = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| {
+            value.starts_with("application/x-ndjson") || value.starts_with("application/jsonl")
+        });
+
+    if !is_ndjson {
+        return Ok(vec![serde_json::from_str(body)?]);
+    }
+    body.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line).with_context(|| format!("Invalid JSON on line {}", i + 1))
+        })
+        .collect()
+}
+
+impl<T: Json + 'static> StreamReader for HttpServerReader<T> {
+    type Item = T;
+
+    async fn stream(&self) -> impl Stream<Item = Self::Item> {
+        let server = self.server.lock().unwrap().take();
+        let receiver = self.receiver.lock().unwrap().take();
+        let addr = self.addr;
+
+        stream! {
+            let (Some((listener, router)), Some(mut receiver)) = (server, receiver) else {
+                log::error!("HTTP server on {addr} is already running");
+                return;
+            };
+            let listener = match TcpListener::from_std(listener) {
+                Ok(listener) => listener,
+                Err(e) => {
+                    log::error!("Failed to start HTTP server on {addr}: {e}");
+                    return;
+                }
+            };
+
+            log::info!("Listening for HTTP requests on {addr}");
+            tokio::spawn(async move {
+                if let Err(e) = axum::serve(listener, router).await {
+                    log::error!("HTTP server on {addr} stopped: {e}");
+                }
+            });
+
+            while let Some(record) = receiver.recv().await {
+                log::trace!("Received record: {record:?}");
+                yield record;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    const SECRET: &[u8] = b"secret";
+    const BODY: &[u8] = br#"{"id":1}"#;
+
+    fn headers(name: &'static str, value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    fn signature(body: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(SECRET).unwrap();
+        mac.update(body);
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    #[test]
+    fn verifies_hmac_signature() {
+        let verification = Verification::hmac_sha256("X-Signature", "unused", "sha256=");
+        let valid = headers("x-signature", &format!("sha256={}", signature(BODY)));
+        assert!(verification.verify(SECRET, &valid, BODY));
+
+        assert!(!verification.verify(SECRET, &valid, br#"{"id":2}"#));
+        let unprefixed = headers("x-signature", &signature(BODY));
+        assert!(!verification.verify(SECRET, &unprefixed, BODY));
+        let not_hex = headers("x-signature", "sha256=zz");
+        assert!(!verification.verify(SECRET, &not_hex, BODY));
+        assert!(!verification.verify(SECRET, &HeaderMap::new(), BODY));
+    }
+
+    #[test]
+    fn verifies_shared_secret() {
+        let verification = Verification::shared_secret("X-Token", "unused");
+        assert!(verification.verify(SECRET, &headers("x-token", "secret"), BODY));
+        assert!(!verification.verify(SECRET, &headers("x-token", "secre"), BODY));
+    }
+}
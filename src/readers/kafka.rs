@@ -1,60 +1,592 @@
 use std::any::type_name;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
 use std::time::Duration;
 
+use anyhow::{Context, Result, anyhow, bail};
 use async_stream::stream;
-use rdkafka::Message;
+use chrono::DateTime;
 use rdkafka::config::ClientConfig;
-use rdkafka::consumer::{Consumer, StreamConsumer};
-use tokio::time::sleep;
+use rdkafka::consumer::{
+    BaseConsumer, Consumer, ConsumerContext, RebalanceProtocol, StreamConsumer,
+};
+use rdkafka::topic_partition_list::{Offset, TopicPartitionList};
+use rdkafka::types::RDKafkaRespErr;
+use rdkafka::{ClientContext, Message};
+use tokio::time::{sleep, timeout};
 use tokio_stream::Stream;
 
 use crate::readers::StreamReader;
 use crate::schemas::Json;
-use crate::schemas::kafka::KafkaMessage;
+use crate::schemas::kafka::{KafkaMessage, KafkaSecurity};
+
+const METADATA_TIMEOUT: Duration = Duration::from_secs(10);
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Where a `KafkaReader` starts consuming a partition the first time it is
+/// assigned, instead of the group's committed offset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StartPosition {
+    Earliest,
+    Latest,
+    /// The first offset whose timestamp (milliseconds since the Unix epoch)
+    /// is at or after the given one. Offsets are looked up once, when the
+    /// stream starts, so partitions created after that start at the group's
+    /// committed offset (or `auto.offset.reset`) rather than the timestamp.
+    Timestamp(i64),
+    /// Explicit `(topic, partition, offset)` triples. Partitions that are not
+    /// listed keep the default behaviour.
+    Offsets(Vec<(String, i32, i64)>),
+}
+
+impl FromStr for StartPosition {
+    type Err = anyhow::Error;
+
+    /// Parses `earliest`, `latest`, `timestamp:<millis | RFC 3339>` or
+    /// `offsets:<topic>:<partition>:<offset>[,...]`.
+    fn from_str(s: &str) -> Result<Self> {
+        let (kind, value) = s.split_once(':').unwrap_or((s, ""));
+        match kind {
+            "earliest" => Ok(StartPosition::Earliest),
+            "latest" => Ok(StartPosition::Latest),
+            "timestamp" => parse_timestamp(value).map(StartPosition::Timestamp),
+            "offsets" => parse_offsets(value).map(StartPosition::Offsets),
+            _ => Err(anyhow!(
+                "Invalid start position '{s}', expected earliest, latest, timestamp:<value> or offsets:<list>"
+            )),
+        }
+    }
+}
+
+/// Where a bounded `KafkaReader` stops. Each partition ends just before the
+/// resolved offset, and the stream finishes once every partition has ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EndPosition {
+    /// The high watermark of each partition when the stream starts.
+    HighWatermark,
+    /// The first offset whose timestamp (milliseconds since the Unix epoch)
+    /// is at or after the given one.
+    Timestamp(i64),
+    /// Explicit `(topic, partition, offset)` triples. Partitions that are not
+    /// listed end at their high watermark.
+    Offsets(Vec<(String, i32, i64)>),
+}
+
+impl FromStr for EndPosition {
+    type Err = anyhow::Error;
+
+    /// Parses `high-watermark`, `timestamp:<millis | RFC 3339>` or
+    /// `offsets:<topic>:<partition>:<offset>[,...]`.
+    fn from_str(s: &str) -> Result<Self> {
+        let (kind, value) = s.split_once(':').unwrap_or((s, ""));
+        match kind {
+            "high-watermark" => Ok(EndPosition::HighWatermark),
+            "timestamp" => parse_timestamp(value).map(EndPosition::Timestamp),
+            "offsets" => parse_offsets(value).map(EndPosition::Offsets),
+            _ => Err(anyhow!(
+                "Invalid end position '{s}', expected high-watermark, timestamp:<value> or offsets:<list>"
+            )),
+        }
+    }
+}
+
+fn parse_timestamp(value: &str) -> Result<i64> {
+    match value.parse::<i64>() {
+        Ok(millis) => Ok(millis),
+        Err(_) => Ok(DateTime::parse_from_rfc3339(value)
+            .with_context(|| format!("Invalid timestamp '{value}'"))?
+            .timestamp_millis()),
+    }
+}
+
+fn parse_offsets(value: &str) -> Result<Vec<(String, i32, i64)>> {
+    value
+        .split(',')
+        .map(|entry| {
+            let parts: Vec<&str> = entry.rsplitn(3, ':').collect();
+            let [offset, partition, topic] = parts[..] else {
+                bail!("Invalid offset '{entry}', expected <topic>:<partition>:<offset>");
+            };
+            Ok((
+                topic.to_string(),
+                partition
+                    .parse()
+                    .with_context(|| format!("Invalid partition in '{entry}'"))?,
+                offset
+                    .parse()
+                    .with_context(|| format!("Invalid offset in '{entry}'"))?,
+            ))
+        })
+        .collect()
+}
+
+/// A [`StartPosition`] resolved to the offset each partition starts at, so
+/// that assigning partitions needs no request to the brokers.
+enum StartOffsets {
+    /// Every partition starts at this offset.
+    All(Offset),
+    /// Only the listed partitions are moved.
+    Partitions(HashMap<(String, i32), Offset>),
+}
+
+/// Consumer context that moves newly assigned partitions to the configured
+/// `StartPosition` before they are fetched.
+pub struct KafkaReaderContext {
+    /// Set when the stream starts, before the reader subscribes.
+    start_offsets: OnceLock<StartOffsets>,
+    started: Mutex<HashSet<(String, i32)>>,
+}
+
+impl KafkaReaderContext {
+    fn new() -> Self {
+        Self {
+            start_offsets: OnceLock::new(),
+            started: Mutex::new(HashSet::new()),
+        }
+    }
+
+    fn apply_start_position(&self, assignment: &mut TopicPartitionList) -> Result<()> {
+        let Some(start_offsets) = self.start_offsets.get() else {
+            return Ok(());
+        };
+
+        // Only rewind partitions the first time we see them, so a later
+        // rebalance does not replay them again.
+        let mut started = self.started.lock().unwrap();
+        let new_partitions: Vec<(String, i32)> = assignment
+            .elements()
+            .iter()
+            .map(|e| (e.topic().to_string(), e.partition()))
+            .filter(|p| !started.contains(p))
+            .collect();
+
+        for (topic, partition) in new_partitions {
+            let offset = match start_offsets {
+                StartOffsets::All(offset) => *offset,
+                StartOffsets::Partitions(offsets) => {
+                    match offsets.get(&(topic.clone(), partition)) {
+                        Some(offset) => *offset,
+                        None => continue,
+                    }
+                }
+            };
+            log::info!("Starting topic '{topic}' partition {partition} at {offset:?}");
+            assignment.set_partition_offset(&topic, partition, offset)?;
+        }
+        started.extend(
+            assignment
+                .elements()
+                .iter()
+                .map(|e| (e.topic().to_string(), e.partition())),
+        );
+
+        Ok(())
+    }
+}
+
+impl ClientContext for KafkaReaderContext {}
+
+impl ConsumerContext for KafkaReaderContext {
+    fn rebalance(
+        &self,
+        base_consumer: &BaseConsumer<Self>,
+        err: RDKafkaRespErr,
+        tpl: &mut TopicPartitionList,
+    ) {
+        let cooperative = matches!(
+            base_consumer.rebalance_protocol(),
+            RebalanceProtocol::Cooperative
+        );
+
+        let result = match err {
+            RDKafkaRespErr::RD_KAFKA_RESP_ERR__ASSIGN_PARTITIONS => {
+                if let Err(e) = self.apply_start_position(tpl) {
+                    log::error!("Failed to apply start position, using default offsets: {e:?}");
+                }
+                if cooperative {
+                    base_consumer.incremental_assign(tpl)
+                } else {
+                    base_consumer.assign(tpl)
+                }
+            }
+            RDKafkaRespErr::RD_KAFKA_RESP_ERR__REVOKE_PARTITIONS if cooperative => {
+                base_consumer.incremental_unassign(tpl)
+            }
+            RDKafkaRespErr::RD_KAFKA_RESP_ERR__REVOKE_PARTITIONS => base_consumer.unassign(),
+            _ => {
+                log::error!("Error rebalancing: {err:?}");
+                base_consumer.unassign()
+            }
+        };
+
+        if let Err(e) = result {
+            log::error!("Failed to apply rebalance: {e:?}");
+        }
+    }
+}
 
 pub struct KafkaReader<T> {
-    consumer: StreamConsumer,
+    consumer: Arc<StreamConsumer<KafkaReaderContext>>,
+    topics: Vec<String>,
+    start_position: Option<StartPosition>,
+    end_position: Option<EndPosition>,
+    /// Set when the stream stopped before reaching its end position.
+    failed: AtomicBool,
     _marker: std::marker::PhantomData<T>,
 }
 
 impl<T: Json> KafkaReader<T> {
     pub fn new(brokers: &str, group_id: &str, topics: Vec<&str>) -> Self {
-        let consumer: StreamConsumer = ClientConfig::new()
+        Self::try_new(brokers, group_id, topics).expect("Kafka Consumer creation failed")
+    }
+
+    pub fn try_new(brokers: &str, group_id: &str, topics: Vec<&str>) -> Result<Self> {
+        Self::builder(brokers, group_id, topics).build()
+    }
+
+    pub fn builder(brokers: &str, group_id: &str, topics: Vec<&str>) -> KafkaReaderBuilder<T> {
+        KafkaReaderBuilder::new(brokers, group_id, topics)
+    }
+}
+
+/// Builds a [`KafkaReader`], letting any librdkafka consumer property be set
+/// on top of (or in place of) courier's defaults.
+pub struct KafkaReaderBuilder<T> {
+    config: ClientConfig,
+    topics: Vec<String>,
+    security: Option<KafkaSecurity>,
+    start_position: Option<StartPosition>,
+    end_position: Option<EndPosition>,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Json> KafkaReaderBuilder<T> {
+    fn new(brokers: &str, group_id: &str, topics: Vec<&str>) -> Self {
+        let mut config = ClientConfig::new();
+        config
             .set("group.id", group_id)
             .set("bootstrap.servers", brokers)
             .set("enable.partition.eof", "false")
             .set("session.timeout.ms", "6000")
-            .set("enable.auto.commit", "false")
-            .create()
-            .expect("Kafka Consumer creation failed");
-
-        consumer
-            .subscribe(&topics)
-            .expect("Can't subscribe to specified topics");
+            .set("enable.auto.commit", "false");
 
         Self {
-            consumer,
+            config,
+            topics: topics.into_iter().map(Into::into).collect(),
+            security: None,
+            start_position: None,
+            end_position: None,
             _marker: std::marker::PhantomData,
         }
     }
+
+    /// Sets a librdkafka property, overriding the default if there is one.
+    pub fn with_property(mut self, key: &str, value: &str) -> Self {
+        self.config.set(key, value);
+        self
+    }
+
+    pub fn with_properties<K, V>(mut self, properties: impl IntoIterator<Item = (K, V)>) -> Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        for (key, value) in properties {
+            self.config.set(key, value);
+        }
+        self
+    }
+
+    pub fn with_security(mut self, security: KafkaSecurity) -> Self {
+        self.security = Some(security);
+        self
+    }
+
+    pub fn with_start_position(mut self, start_position: StartPosition) -> Self {
+        self.start_position = Some(start_position);
+        self
+    }
+
+    /// Makes the reader bounded: its stream finishes once every partition
+    /// reaches `end_position`.
+    pub fn with_end_position(mut self, end_position: EndPosition) -> Self {
+        self.end_position = Some(end_position);
+        self
+    }
+
+    pub fn build(mut self) -> Result<KafkaReader<T>> {
+        if let Some(security) = &self.security {
+            security
+                .apply(&mut self.config)
+                .context("Invalid Kafka security configuration")?;
+        }
+
+        let context = KafkaReaderContext::new();
+        let consumer: StreamConsumer<KafkaReaderContext> =
+            self.config.create_with_context(context).with_context(|| {
+                format!(
+                    "Failed to create Kafka consumer for brokers '{}'",
+                    self.config.get("bootstrap.servers").unwrap_or_default()
+                )
+            })?;
+
+        Ok(KafkaReader {
+            consumer: Arc::new(consumer),
+            topics: self.topics,
+            start_position: self.start_position,
+            end_position: self.end_position,
+            failed: AtomicBool::new(false),
+            _marker: std::marker::PhantomData,
+        })
+    }
+}
+
+/// Returns the partition IDs of `topic`.
+fn topic_partitions<C: ConsumerContext>(
+    consumer: &impl Consumer<C>,
+    topic: &str,
+) -> Result<Vec<i32>> {
+    let metadata = consumer
+        .fetch_metadata(Some(topic), METADATA_TIMEOUT)
+        .with_context(|| format!("Failed to fetch metadata for topic '{topic}'"))?;
+    metadata
+        .topics()
+        .iter()
+        .find(|t| t.name() == topic)
+        .map(|t| t.partitions().iter().map(|p| p.id()).collect())
+        .with_context(|| format!("Topic '{topic}' not found in metadata"))
+}
+
+/// Resolves `start_position` for every partition of `topics`. Timestamps are
+/// looked up now, so that a failure stops the reader rather than leaving it at
+/// the committed offsets. The lookups block for up to `METADATA_TIMEOUT` each.
+fn resolve_start_offsets<C: ConsumerContext>(
+    consumer: &impl Consumer<C>,
+    topics: &[String],
+    start_position: &StartPosition,
+) -> Result<StartOffsets> {
+    match start_position {
+        StartPosition::Earliest => Ok(StartOffsets::All(Offset::Beginning)),
+        StartPosition::Latest => Ok(StartOffsets::All(Offset::End)),
+        StartPosition::Offsets(offsets) => Ok(StartOffsets::Partitions(
+            offsets
+                .iter()
+                .map(|(topic, partition, offset)| {
+                    ((topic.clone(), *partition), Offset::Offset(*offset))
+                })
+                .collect(),
+        )),
+        StartPosition::Timestamp(timestamp) => {
+            let mut timestamps = TopicPartitionList::new();
+            for topic in topics {
+                for partition in topic_partitions(consumer, topic)? {
+                    timestamps.add_partition_offset(
+                        topic,
+                        partition,
+                        Offset::Offset(*timestamp),
+                    )?;
+                }
+            }
+            let offsets = consumer
+                .offsets_for_times(timestamps, METADATA_TIMEOUT)
+                .context("Failed to look up offsets for the start timestamp")?;
+            Ok(StartOffsets::Partitions(
+                offsets
+                    .elements()
+                    .iter()
+                    .map(|e| ((e.topic().to_string(), e.partition()), e.offset()))
+                    .collect(),
+            ))
+        }
+    }
+}
+
+/// Resolves `end_position` to an exclusive end offset for every partition of
+/// `topics`. Partitions that are already empty are left out. The lookups block
+/// for up to `METADATA_TIMEOUT` each.
+fn resolve_end_offsets<C: ConsumerContext>(
+    consumer: &impl Consumer<C>,
+    topics: &[String],
+    end_position: &EndPosition,
+) -> Result<HashMap<(String, i32), i64>> {
+    let mut end_offsets = HashMap::new();
+
+    for topic in topics {
+        for partition in topic_partitions(consumer, topic)? {
+            let (low, high) = consumer
+                .fetch_watermarks(topic, partition, METADATA_TIMEOUT)
+                .with_context(|| {
+                    format!("Failed to fetch watermarks for '{topic}' partition {partition}")
+                })?;
+
+            let end = match end_position {
+                EndPosition::HighWatermark => high,
+                EndPosition::Offsets(offsets) => offsets
+                    .iter()
+                    .find(|(t, p, _)| t == topic && *p == partition)
+                    .map_or(high, |(_, _, offset)| *offset),
+                EndPosition::Timestamp(timestamp) => {
+                    let mut timestamps = TopicPartitionList::new();
+                    timestamps.add_partition_offset(
+                        topic,
+                        partition,
+                        Offset::Offset(*timestamp),
+                    )?;
+                    let offsets = consumer.offsets_for_times(timestamps, METADATA_TIMEOUT)?;
+                    match offsets.find_partition(topic, partition).map(|e| e.offset()) {
+                        Some(Offset::Offset(offset)) => offset,
+                        _ => high,
+                    }
+                }
+            };
+
+            log::debug!("Topic '{topic}' partition {partition} ends at offset {end}");
+            if end > low {
+                end_offsets.insert((topic.clone(), partition), end);
+            }
+        }
+    }
+
+    Ok(end_offsets)
+}
+
+impl<T> KafkaReader<T> {
+    /// Resolves the start position on the blocking pool, then subscribes, so
+    /// that partitions are only assigned once their start offsets are known.
+    async fn subscribe(&self) -> Result<()> {
+        if let Some(start_position) = &self.start_position {
+            let consumer = self.consumer.clone();
+            let topics = self.topics.clone();
+            let start_position = start_position.clone();
+            let start_offsets = tokio::task::spawn_blocking(move || {
+                resolve_start_offsets(consumer.as_ref(), &topics, &start_position)
+            })
+            .await?
+            .context("Failed to resolve Kafka start position")?;
+            let _ = self.consumer.context().start_offsets.set(start_offsets);
+        }
+
+        let topics: Vec<&str> = self.topics.iter().map(String::as_str).collect();
+        self.consumer
+            .subscribe(&topics)
+            .with_context(|| format!("Failed to subscribe to topics {topics:?}"))
+    }
+
+    /// Resolves the end offsets on the blocking pool, so that the lookups do
+    /// not stall the runtime.
+    async fn end_offsets(&self, end_position: &EndPosition) -> Result<HashMap<(String, i32), i64>> {
+        let consumer = self.consumer.clone();
+        let topics = self.topics.clone();
+        let end_position = end_position.clone();
+        tokio::task::spawn_blocking(move || {
+            resolve_end_offsets(consumer.as_ref(), &topics, &end_position)
+        })
+        .await?
+    }
+
+    /// Drops partitions whose consumer position already reached their end
+    /// offset, which catches partitions that end without a message to see.
+    fn drop_finished_partitions(&self, end_offsets: &mut HashMap<(String, i32), i64>) {
+        let Ok(positions) = self.consumer.position() else {
+            return;
+        };
+        for e in positions.elements() {
+            let key = (e.topic().to_string(), e.partition());
+            if let (Offset::Offset(position), Some(end)) = (e.offset(), end_offsets.get(&key))
+                && position >= *end
+            {
+                log::debug!(
+                    "Topic '{}' partition {} reached its end offset",
+                    key.0,
+                    key.1
+                );
+                end_offsets.remove(&key);
+            }
+        }
+    }
 }
 
 impl<T: Json> StreamReader for KafkaReader<T> {
     type Item = KafkaMessage<T>;
 
+    /// A bounded stream that failed is reported as having ended unexpectedly
+    /// rather than as complete.
+    fn is_bounded(&self) -> bool {
+        self.end_position.is_some() && !self.failed.load(Ordering::Relaxed)
+    }
+
     async fn stream(&self) -> impl Stream<Item = Self::Item> {
         stream! {
+            if let Err(e) = self.subscribe().await {
+                log::error!("{e:?}");
+                self.failed.store(true, Ordering::Relaxed);
+                return;
+            }
+
+            let mut end_offsets = None;
+            if let Some(end_position) = &self.end_position {
+                match self.end_offsets(end_position).await {
+                    Ok(offsets) => end_offsets = Some(offsets),
+                    Err(e) => {
+                        log::error!("Failed to resolve end offsets: {e:?}");
+                        self.failed.store(true, Ordering::Relaxed);
+                        return;
+                    }
+                }
+            }
+
             loop {
+                if end_offsets.as_ref().is_some_and(HashMap::is_empty) {
+                    log::info!("Reached the end offset of every partition");
+                    break;
+                }
+
                 log::debug!("Waiting for next message");
                 let start = std::time::Instant::now();
 
-                match self.consumer.recv().await {
+                let received = match &mut end_offsets {
+                    Some(end_offsets) => {
+                        match timeout(IDLE_CHECK_INTERVAL, self.consumer.recv()).await {
+                            Ok(received) => received,
+                            Err(_) => {
+                                self.drop_finished_partitions(end_offsets);
+                                continue;
+                            }
+                        }
+                    }
+                    None => self.consumer.recv().await,
+                };
+
+                match received {
                     Ok(m) => {
                         let m = m.detach();
                         let offset = m.offset();
                         let partition = m.partition();
                         let topic = m.topic();
 
+                        if let Some(end_offsets) = &mut end_offsets {
+                            let key = (topic.to_string(), partition);
+                            match end_offsets.get(&key) {
+                                Some(&end) if offset < end => {
+                                    if offset + 1 >= end {
+                                        log::debug!(
+                                            "Topic '{topic}' partition {partition} reached its end offset"
+                                        );
+                                        end_offsets.remove(&key);
+                                    }
+                                }
+                                _ => {
+                                    log::trace!(
+                                        "Skipping message past the end of topic '{topic}' partition {partition}"
+                                    );
+                                    end_offsets.remove(&key);
+                                    continue;
+                                }
+                            }
+                        }
+
                         log::debug!(
                             "Received message from topic '{topic}': (partition: {partition}, offset: {offset})",
                         );
@@ -5,14 +5,43 @@ use anyhow::Result;
 use async_trait::async_trait;
 use tokio_stream::Stream;
 
+pub mod amqp;
 pub mod api;
+pub mod directory;
+pub mod file;
+pub mod http_server;
 pub mod kafka;
+pub mod mqtt;
+pub mod nats;
+pub mod redis;
+pub mod sql;
+pub mod stdin;
 
 pub trait StreamReader: Sync + Send {
     type Item: Send;
 
     fn stream(&self) -> impl Future<Output = impl Stream<Item = Self::Item> + Send> + Send;
 
+    /// Whether the stream is expected to finish, rather than only ending when
+    /// something goes wrong.
+    fn is_bounded(&self) -> bool {
+        false
+    }
+
+    /// Called once the last item from the stream has been written, so that
+    /// readers tracking delivery can confirm it to their source. Does nothing
+    /// by default.
+    fn acknowledge(&self) -> impl Future<Output = Result<()>> + Send {
+        async { Ok(()) }
+    }
+
+    /// Called instead of [`acknowledge`](StreamReader::acknowledge) when the
+    /// last item could not be written, so that readers tracking delivery can
+    /// hand it back to their source. Does nothing by default.
+    fn reject(&self) -> impl Future<Output = Result<()>> + Send {
+        async { Ok(()) }
+    }
+
     fn set_id(&mut self, _id: &'static str) {}
 
     fn get_id(&self) -> &'static str {
@@ -24,7 +53,22 @@ pub trait StreamReader: Sync + Send {
 pub trait Reader: Sync + Send {
     type Item: Send + Sync;
 
-    async fn read(&self) -> Result<Self::Item>;
+    /// Reads the data available now, or `None` if nothing is new since the
+    /// last read.
+    async fn read(&self) -> Result<Option<Self::Item>>;
+
+    /// Reads every record available now. Readers that can return several
+    /// records at once override this; by default it wraps `read`.
+    async fn read_batch(&self) -> Result<Vec<Self::Item>> {
+        Ok(self.read().await?.into_iter().collect())
+    }
+
+    /// Called once everything returned by the last read has been written, so
+    /// that readers tracking their position, such as a watermark, only move
+    /// past records that were delivered. Does nothing by default.
+    async fn commit(&self) -> Result<()> {
+        Ok(())
+    }
 
     fn set_id(&mut self, _id: &str) {}
 
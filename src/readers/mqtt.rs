@@ -0,0 +1,254 @@
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use async_stream::stream;
+use rumqttc::{
+    AsyncClient, Event, MqttOptions, Packet, Publish, SubscribeFilter, SubscribeReasonCode,
+};
+use tokio::sync::mpsc::channel;
+use tokio::time::sleep;
+use tokio_stream::Stream;
+
+use crate::mqtt::{EventLoopTask, MqttSettings, QoS, RETRY_DELAY, client};
+use crate::readers::StreamReader;
+use crate::schemas::Json;
+use crate::schemas::kafka::KafkaMessage;
+use crate::secret::Secret;
+
+const DEFAULT_BUFFER: usize = 1000;
+
+/// Subscribes to MQTT topic filters, which may use the `+` and `#`
+/// wildcards, and reads one JSON record per message, keyed by its topic.
+/// Messages that are not valid records are logged and skipped.
+///
+/// Messages are acknowledged once they have been written, in the order they
+/// were received. With a persistent session, a failed write makes the reader
+/// reconnect without acknowledging the message, so with QoS 1 or 2 the broker
+/// sends it again, along with the messages received after it. With a clean
+/// session, reconnecting would lose every message not yet written, so a
+/// message that failed to be written is acknowledged and lost instead. Use a
+/// persistent session with a client ID of its own for the broker to keep
+/// messages, and the subscriptions, while the reader is away.
+pub struct MqttReader<T: Json> {
+    options: MqttOptions,
+    filters: Vec<SubscribeFilter>,
+    buffer: usize,
+    client: Mutex<Option<AsyncClient>>,
+    /// Message yielded last, acknowledged once it has been written.
+    pending: Mutex<Option<Publish>>,
+    /// Set when the message yielded last failed to be written and the
+    /// session is persistent, so the reader reconnects.
+    rejected: AtomicBool,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Json> MqttReader<T> {
+    pub fn builder(broker: &str, client_id: &str, topics: Vec<&str>) -> MqttReaderBuilder<T> {
+        MqttReaderBuilder::new(broker, client_id, topics)
+    }
+}
+
+/// Builds an [`MqttReader`]. Topics are subscribed with QoS 1 unless
+/// configured otherwise.
+pub struct MqttReaderBuilder<T: Json> {
+    settings: MqttSettings,
+    topics: Vec<String>,
+    qos: QoS,
+    buffer: usize,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Json> MqttReaderBuilder<T> {
+    fn new(broker: &str, client_id: &str, topics: Vec<&str>) -> Self {
+        Self {
+            settings: MqttSettings::new(broker, client_id),
+            topics: topics.into_iter().map(String::from).collect(),
+            qos: QoS::AtLeastOnce,
+            buffer: DEFAULT_BUFFER,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    pub fn with_qos(mut self, qos: QoS) -> Self {
+        self.qos = qos;
+        self
+    }
+
+    pub fn with_credentials(mut self, username: &str, password: impl Into<Secret>) -> Self {
+        self.settings.set_credentials(username, password.into());
+        self
+    }
+
+    pub fn with_keep_alive(mut self, keep_alive: Duration) -> Self {
+        self.settings.set_keep_alive(keep_alive);
+        self
+    }
+
+    /// Asks the broker to keep the session when disconnected, instead of
+    /// starting a clean one.
+    pub fn with_persistent_session(mut self) -> Self {
+        self.settings.set_persistent_session();
+        self
+    }
+
+    /// Messages that may wait for the writer before the connection stops
+    /// being read. Defaults to 1000.
+    pub fn with_buffer(mut self, buffer: usize) -> Self {
+        self.buffer = buffer;
+        self
+    }
+
+    pub fn build(self) -> Result<MqttReader<T>> {
+        if self.topics.is_empty() {
+            bail!("MQTT reader needs at least one topic");
+        }
+        if self.buffer == 0 {
+            bail!("MQTT reader buffer must hold at least one message");
+        }
+        let mut options = self.settings.options()?;
+        options.set_manual_acks(true);
+        let filters = self
+            .topics
+            .into_iter()
+            .map(|topic| SubscribeFilter::new(topic, self.qos))
+            .collect();
+
+        Ok(MqttReader {
+            options,
+            filters,
+            buffer: self.buffer,
+            client: Mutex::new(None),
+            pending: Mutex::new(None),
+            rejected: AtomicBool::new(false),
+            _marker: std::marker::PhantomData,
+        })
+    }
+}
+
+impl<T: Json> MqttReader<T> {
+    /// Connects and forwards incoming messages to the returned receiver until
+    /// it is dropped, along with the task doing so.
+    fn connect(&self) -> (tokio::sync::mpsc::Receiver<Publish>, EventLoopTask) {
+        let (client, mut event_loop) = client(self.options.clone());
+        *self.client.lock().unwrap() = Some(client.clone());
+        let filters = self.filters.clone();
+        let (sender, receiver) = channel(self.buffer);
+
+        let task = tokio::spawn(async move {
+            loop {
+                match event_loop.poll().await {
+                    Ok(Event::Incoming(Packet::ConnAck(ack))) => {
+                        log::info!("Connected to MQTT broker");
+                        if ack.session_present {
+                            continue;
+                        }
+                        if let Err(e) = client.subscribe_many(filters.clone()).await {
+                            log::error!("Failed to subscribe to MQTT topics: {e}");
+                        }
+                    }
+                    Ok(Event::Incoming(Packet::SubAck(ack))) => {
+                        let failed = ack
+                            .return_codes
+                            .iter()
+                            .filter(|code| **code == SubscribeReasonCode::Failure)
+                            .count();
+                        match failed {
+                            0 => log::debug!("Subscribed to {} MQTT topic(s)", filters.len()),
+                            _ => log::error!("Broker refused {failed} MQTT subscription(s)"),
+                        }
+                    }
+                    Ok(Event::Incoming(Packet::Publish(publish))) => {
+                        if sender.send(publish).await.is_err() {
+                            break;
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        log::error!("MQTT connection error: {e}");
+                        sleep(RETRY_DELAY).await;
+                    }
+                }
+            }
+        });
+        (receiver, EventLoopTask(task))
+    }
+
+    async fn ack(&self, publish: &Publish) -> Result<()> {
+        let client = self.client.lock().unwrap().clone();
+        if let Some(client) = client {
+            client
+                .ack(publish)
+                .await
+                .with_context(|| format!("Failed to acknowledge message on '{}'", publish.topic))?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Json> StreamReader for MqttReader<T> {
+    type Item = KafkaMessage<T>;
+
+    async fn stream(&self) -> impl Stream<Item = Self::Item> {
+        stream! {
+            loop {
+                let (mut receiver, task) = self.connect();
+                while let Some(publish) = receiver.recv().await {
+                    log::debug!("Received message on '{}'", publish.topic);
+                    match serde_json::from_slice::<T>(&publish.payload) {
+                        Ok(record) => {
+                            let message = KafkaMessage::new(&publish.topic, record);
+                            *self.pending.lock().unwrap() = Some(publish);
+                            yield message;
+                            if self.rejected.load(Ordering::Relaxed) {
+                                break;
+                            }
+                        }
+                        Err(e) => {
+                            log::error!("Skipping invalid message on '{}': {e}", publish.topic);
+                            if let Err(e) = self.ack(&publish).await {
+                                log::error!("{e:?}");
+                            }
+                        }
+                    }
+                }
+                if !self.rejected.swap(false, Ordering::Relaxed) {
+                    return;
+                }
+
+                // Acknowledging later messages would leave the rejected one
+                // unacknowledged for the rest of the session. The session is
+                // persistent, so the broker sends it again.
+                log::warn!("Reconnecting to MQTT broker to receive unwritten messages again");
+                drop(receiver);
+                drop(task);
+                sleep(RETRY_DELAY).await;
+            }
+        }
+    }
+
+    async fn acknowledge(&self) -> Result<()> {
+        let pending = self.pending.lock().unwrap().take();
+        match pending {
+            Some(publish) => self.ack(&publish).await,
+            None => Ok(()),
+        }
+    }
+
+    async fn reject(&self) -> Result<()> {
+        let pending = self.pending.lock().unwrap().take();
+        let Some(publish) = pending else {
+            return Ok(());
+        };
+        if self.options.clean_session() {
+            log::warn!(
+                "Dropping message on '{}', as a clean session would not send it again",
+                publish.topic
+            );
+            return self.ack(&publish).await;
+        }
+        self.rejected.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+}
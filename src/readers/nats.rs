@@ -0,0 +1,258 @@
+use std::sync::Mutex;
+
+use anyhow::{Context, Result, anyhow, bail};
+use async_nats::jetstream::consumer::{AckPolicy, pull};
+use async_nats::jetstream::{self, AckKind};
+use async_nats::{Client, Subscriber};
+use async_stream::stream;
+use tokio::time::sleep;
+use tokio_stream::{Stream, StreamExt};
+
+use crate::nats::{NatsConnection, NatsSettings, RETRY_DELAY};
+use crate::readers::StreamReader;
+use crate::schemas::Json;
+use crate::schemas::kafka::KafkaMessage;
+use crate::secret::Secret;
+
+/// Where the reader gets its messages from.
+enum Source {
+    /// Core NATS subscription, optionally shared by a queue group.
+    Core { queue_group: Option<String> },
+    /// Durable pull consumer on a JetStream stream.
+    JetStream { stream: String, durable: String },
+}
+
+/// Reads one JSON record per NATS message on a subject, which may use the
+/// `*` and `>` wildcards, keyed by the message's subject.
+///
+/// With core NATS, messages published while the reader is away, or that fail
+/// to be written, are lost. With JetStream, the reader uses a durable
+/// consumer and acknowledges each message once it has been written; those
+/// that failed are negatively acknowledged, so the server delivers them again
+/// straight away, after messages already sent to the reader.
+/// Messages that are not valid records are logged and skipped, and JetStream
+/// is told not to deliver them again.
+pub struct NatsReader<T: Json> {
+    connection: NatsConnection,
+    subject: String,
+    source: Source,
+    /// JetStream message yielded last, acknowledged once it has been written
+    /// or negatively acknowledged if that failed.
+    pending: Mutex<Option<jetstream::Message>>,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Json> NatsReader<T> {
+    pub fn builder(url: impl Into<Secret>, subject: &str) -> NatsReaderBuilder<T> {
+        NatsReaderBuilder::new(url, subject)
+    }
+}
+
+/// Builds a [`NatsReader`], subscribing with core NATS unless configured to
+/// use JetStream.
+pub struct NatsReaderBuilder<T: Json> {
+    settings: NatsSettings,
+    subject: String,
+    queue_group: Option<String>,
+    jetstream: Option<(String, String)>,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Json> NatsReaderBuilder<T> {
+    fn new(url: impl Into<Secret>, subject: &str) -> Self {
+        Self {
+            settings: NatsSettings::new(url.into()),
+            subject: subject.into(),
+            queue_group: None,
+            jetstream: None,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    /// Authenticates with a `.creds` file holding a user JWT and NKey seed.
+    pub fn with_credentials_file(mut self, path: &str) -> Self {
+        self.settings.set_credentials_file(path);
+        self
+    }
+
+    /// Joins a queue group, so that each message goes to only one of the
+    /// readers in it. Core NATS only.
+    pub fn with_queue_group(mut self, queue_group: &str) -> Self {
+        self.queue_group = Some(queue_group.into());
+        self
+    }
+
+    /// Reads from a JetStream stream through the named durable consumer,
+    /// which is created if it does not exist. The stream must already exist.
+    pub fn with_jetstream(mut self, stream: &str, durable: &str) -> Self {
+        self.jetstream = Some((stream.into(), durable.into()));
+        self
+    }
+
+    pub fn build(self) -> Result<NatsReader<T>> {
+        if self.subject.is_empty() {
+            bail!("NATS reader needs a subject");
+        }
+        let source = match (self.jetstream, self.queue_group) {
+            (None, queue_group) => Source::Core { queue_group },
+            (Some(_), Some(_)) => bail!("NATS queue groups cannot be used with JetStream"),
+            (Some((stream, durable)), None) => {
+                if stream.is_empty() || durable.is_empty() {
+                    bail!("NATS JetStream reader needs a stream and a durable consumer name");
+                }
+                Source::JetStream { stream, durable }
+            }
+        };
+
+        Ok(NatsReader {
+            connection: self.settings.resolve()?,
+            subject: self.subject,
+            source,
+            pending: Mutex::new(None),
+            _marker: std::marker::PhantomData,
+        })
+    }
+}
+
+impl<T: Json> NatsReader<T> {
+    async fn subscribe(&self, client: &Client, queue_group: &Option<String>) -> Result<Subscriber> {
+        let subscriber = match queue_group {
+            Some(group) => {
+                client
+                    .queue_subscribe(self.subject.clone(), group.clone())
+                    .await
+            }
+            None => client.subscribe(self.subject.clone()).await,
+        };
+        subscriber.with_context(|| format!("Failed to subscribe to '{}'", self.subject))
+    }
+
+    async fn consume(&self, client: Client, stream: &str, durable: &str) -> Result<pull::Stream> {
+        let stream = jetstream::new(client)
+            .get_stream(stream)
+            .await
+            .with_context(|| format!("Failed to get JetStream stream '{stream}'"))?;
+        let config = pull::Config {
+            durable_name: Some(durable.into()),
+            filter_subject: self.subject.clone(),
+            ack_policy: AckPolicy::Explicit,
+            ..Default::default()
+        };
+        let consumer = stream
+            .get_or_create_consumer(durable, config)
+            .await
+            .with_context(|| format!("Failed to get JetStream consumer '{durable}'"))?;
+        consumer
+            .messages()
+            .await
+            .with_context(|| format!("Failed to consume from '{durable}'"))
+    }
+}
+
+impl<T: Json> StreamReader for NatsReader<T> {
+    type Item = KafkaMessage<T>;
+
+    async fn stream(&self) -> impl Stream<Item = Self::Item> {
+        stream! {
+            loop {
+                let client = match self.connection.connect().await {
+                    Ok(client) => client,
+                    Err(e) => {
+                        log::error!("{e:?}");
+                        sleep(RETRY_DELAY).await;
+                        continue;
+                    }
+                };
+
+                match &self.source {
+                    Source::Core { queue_group } => {
+                        let mut subscriber = match self.subscribe(&client, queue_group).await {
+                            Ok(subscriber) => subscriber,
+                            Err(e) => {
+                                log::error!("{e:?}");
+                                sleep(RETRY_DELAY).await;
+                                continue;
+                            }
+                        };
+                        log::info!("Subscribed to '{}'", self.subject);
+
+                        while let Some(message) = subscriber.next().await {
+                            log::debug!("Received message on '{}'", message.subject);
+                            match serde_json::from_slice::<T>(&message.payload) {
+                                Ok(record) => yield KafkaMessage::new(message.subject.as_str(), record),
+                                Err(e) => {
+                                    log::error!("Skipping invalid message on '{}': {e}", message.subject);
+                                }
+                            }
+                        }
+                    }
+                    Source::JetStream { stream, durable } => {
+                        let mut messages = match self.consume(client, stream, durable).await {
+                            Ok(messages) => messages,
+                            Err(e) => {
+                                log::error!("{e:?}");
+                                sleep(RETRY_DELAY).await;
+                                continue;
+                            }
+                        };
+                        log::info!("Consuming '{}' from stream '{stream}' as '{durable}'", self.subject);
+
+                        while let Some(message) = messages.next().await {
+                            let message = match message {
+                                Ok(message) => message,
+                                Err(e) => {
+                                    log::error!("Failed to receive from stream '{stream}': {e}");
+                                    break;
+                                }
+                            };
+                            log::debug!("Received message on '{}'", message.subject);
+
+                            match serde_json::from_slice::<T>(&message.payload) {
+                                Ok(record) => {
+                                    let record = KafkaMessage::new(message.subject.as_str(), record);
+                                    *self.pending.lock().unwrap() = Some(message);
+                                    yield record;
+                                }
+                                Err(e) => {
+                                    log::error!("Skipping invalid message on '{}': {e}", message.subject);
+                                    if let Err(e) = message.ack_with(AckKind::Term).await {
+                                        log::error!("Failed to terminate message: {e}");
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+
+                log::warn!("Stopped reading '{}', reconnecting", self.subject);
+                sleep(RETRY_DELAY).await;
+            }
+        }
+    }
+
+    async fn acknowledge(&self) -> Result<()> {
+        let pending = self.pending.lock().unwrap().take();
+        if let Some(message) = pending {
+            message.ack().await.map_err(|e| {
+                anyhow!(
+                    "Failed to acknowledge message on '{}': {e}",
+                    message.subject
+                )
+            })?;
+        }
+        Ok(())
+    }
+
+    async fn reject(&self) -> Result<()> {
+        let pending = self.pending.lock().unwrap().take();
+        if let Some(message) = pending {
+            message.ack_with(AckKind::Nak(None)).await.map_err(|e| {
+                anyhow!(
+                    "Failed to negatively acknowledge message on '{}': {e}",
+                    message.subject
+                )
+            })?;
+        }
+        Ok(())
+    }
+}
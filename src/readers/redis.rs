@@ -0,0 +1,469 @@
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::{Context, Result, bail};
+use async_stream::stream;
+use redis::AsyncCommands;
+use redis::streams::{StreamId, StreamReadOptions, StreamReadReply};
+use tokio::time::sleep;
+use tokio_stream::{Stream, StreamExt};
+
+use crate::readers::StreamReader;
+use crate::redis::{BLOCK_TIMEOUT, RETRY_DELAY, RedisConnection};
+use crate::schemas::Json;
+use crate::secret::Secret;
+
+const DEFAULT_BATCH_SIZE: usize = 100;
+const DEFAULT_FIELD: &str = "data";
+
+/// Consumes a Redis stream as a member of a consumer group. Each entry holds
+/// a JSON record in one field, `data` unless configured otherwise.
+///
+/// An entry is acknowledged with `XACK` once it has been written. An entry
+/// that failed to be written stays pending, and the reader goes back to this
+/// consumer's pending entries, reading it and the entries after it again
+/// before new ones. Pending entries are also read first when the reader
+/// restarts with the same consumer name. Entries that are not valid records
+/// are logged and acknowledged.
+pub struct RedisStreamReader<T: Json> {
+    connection: RedisConnection,
+    stream: String,
+    group: String,
+    consumer: String,
+    start_id: String,
+    batch_size: usize,
+    field: String,
+    /// Entry yielded last, acknowledged once it has been written.
+    pending: Mutex<Option<String>>,
+    /// Set when the entry yielded last failed to be written, so that the
+    /// pending entries are read again.
+    rejected: AtomicBool,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Json> RedisStreamReader<T> {
+    pub fn builder(
+        url: impl Into<Secret>,
+        stream: &str,
+        group: &str,
+        consumer: &str,
+    ) -> RedisStreamReaderBuilder<T> {
+        RedisStreamReaderBuilder::new(url, stream, group, consumer)
+    }
+}
+
+/// Builds a [`RedisStreamReader`]. The group is created, along with the
+/// stream, if it does not exist.
+pub struct RedisStreamReaderBuilder<T: Json> {
+    url: Secret,
+    stream: String,
+    group: String,
+    consumer: String,
+    start_id: String,
+    batch_size: usize,
+    field: String,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Json> RedisStreamReaderBuilder<T> {
+    fn new(url: impl Into<Secret>, stream: &str, group: &str, consumer: &str) -> Self {
+        Self {
+            url: url.into(),
+            stream: stream.into(),
+            group: group.into(),
+            consumer: consumer.into(),
+            start_id: "$".into(),
+            batch_size: DEFAULT_BATCH_SIZE,
+            field: DEFAULT_FIELD.into(),
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    /// Where a newly created group starts: `$` (the default) for entries
+    /// added from now on, `0` for the whole stream, or an entry ID.
+    pub fn with_start_id(mut self, id: &str) -> Self {
+        self.start_id = id.into();
+        self
+    }
+
+    /// Maximum number of entries fetched at once. Defaults to 100.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// Field of each entry holding the record. Defaults to `data`.
+    pub fn with_field(mut self, field: &str) -> Self {
+        self.field = field.into();
+        self
+    }
+
+    pub fn build(self) -> Result<RedisStreamReader<T>> {
+        if self.stream.is_empty() || self.group.is_empty() || self.consumer.is_empty() {
+            bail!("Redis stream reader needs a stream, a group and a consumer");
+        }
+        if self.batch_size == 0 {
+            bail!("Redis stream batch size must be at least 1");
+        }
+        let url = self.url.resolve().context("Failed to resolve Redis URL")?;
+
+        Ok(RedisStreamReader {
+            connection: RedisConnection::open(&url)?,
+            stream: self.stream,
+            group: self.group,
+            consumer: self.consumer,
+            start_id: self.start_id,
+            batch_size: self.batch_size,
+            field: self.field,
+            pending: Mutex::new(None),
+            rejected: AtomicBool::new(false),
+            _marker: std::marker::PhantomData,
+        })
+    }
+}
+
+impl<T: Json> RedisStreamReader<T> {
+    async fn create_group(&self) -> Result<()> {
+        let mut connection = self.connection.get().await?;
+        let created: redis::RedisResult<()> = connection
+            .xgroup_create_mkstream(&self.stream, &self.group, &self.start_id)
+            .await;
+        match created {
+            Ok(()) => {
+                log::info!(
+                    "Created group '{}' on stream '{}' at {}",
+                    self.group,
+                    self.stream,
+                    self.start_id
+                );
+                Ok(())
+            }
+            Err(e) if e.code() == Some("BUSYGROUP") => Ok(()),
+            Err(e) => Err(e).with_context(|| {
+                format!(
+                    "Failed to create group '{}' on stream '{}'",
+                    self.group, self.stream
+                )
+            }),
+        }
+    }
+
+    /// Reads entries after `id`: `>` for new entries, or an entry ID for this
+    /// consumer's pending ones, which are returned without waiting.
+    async fn read_entries(&self, id: &str) -> Result<Vec<StreamId>> {
+        let mut options = StreamReadOptions::default()
+            .group(&self.group, &self.consumer)
+            .count(self.batch_size);
+        if id == ">" {
+            options = options.block(BLOCK_TIMEOUT.as_millis() as usize);
+        }
+
+        let mut connection = self.connection.get().await?;
+        let reply: Option<StreamReadReply> = connection
+            .xread_options(&[&self.stream], &[id], &options)
+            .await
+            .with_context(|| format!("Failed to read stream '{}'", self.stream))?;
+        Ok(reply
+            .into_iter()
+            .flat_map(|reply| reply.keys)
+            .flat_map(|key| key.ids)
+            .collect())
+    }
+
+    async fn ack(&self, id: &str) -> Result<()> {
+        let mut connection = self.connection.get().await?;
+        let _: i64 = connection
+            .xack(&self.stream, &self.group, &[id])
+            .await
+            .with_context(|| format!("Failed to acknowledge entry {id} of '{}'", self.stream))?;
+        log::trace!("Acknowledged entry {id} of '{}'", self.stream);
+        Ok(())
+    }
+
+    fn parse(&self, entry: &StreamId) -> Result<T> {
+        let payload: String = entry
+            .get(&self.field)
+            .with_context(|| format!("Entry has no '{}' field", self.field))?;
+        serde_json::from_str(&payload).context("Invalid record")
+    }
+}
+
+impl<T: Json> StreamReader for RedisStreamReader<T> {
+    type Item = T;
+
+    async fn stream(&self) -> impl Stream<Item = Self::Item> {
+        stream! {
+            while let Err(e) = self.create_group().await {
+                log::error!("{e:?}");
+                sleep(RETRY_DELAY).await;
+            }
+
+            // Entries left pending by an earlier run come first.
+            let mut id = String::from("0");
+            loop {
+                let entries = match self.read_entries(&id).await {
+                    Ok(entries) => entries,
+                    Err(e) => {
+                        log::error!("{e:?}");
+                        sleep(RETRY_DELAY).await;
+                        continue;
+                    }
+                };
+                if id != ">" {
+                    match entries.last() {
+                        Some(last) => {
+                            log::info!("Reading {} pending entries of '{}'", entries.len(), self.stream);
+                            id = last.id.clone();
+                        }
+                        None => id = String::from(">"),
+                    }
+                }
+                log::debug!("Read {} entries from '{}'", entries.len(), self.stream);
+
+                for entry in entries {
+                    match self.parse(&entry) {
+                        Ok(record) => {
+                            *self.pending.lock().unwrap() = Some(entry.id);
+                            yield record;
+                            if self.rejected.swap(false, Ordering::Relaxed) {
+                                log::warn!("Reading the pending entries of '{}' again", self.stream);
+                                id = String::from("0");
+                                sleep(RETRY_DELAY).await;
+                                break;
+                            }
+                        }
+                        Err(e) => {
+                            log::error!("Skipping entry {} of '{}': {e:#}", entry.id, self.stream);
+                            if let Err(e) = self.ack(&entry.id).await {
+                                log::error!("{e:?}");
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    async fn acknowledge(&self) -> Result<()> {
+        let pending = self.pending.lock().unwrap().take();
+        match pending {
+            Some(id) => self.ack(&id).await,
+            None => Ok(()),
+        }
+    }
+
+    async fn reject(&self) -> Result<()> {
+        if self.pending.lock().unwrap().take().is_some() {
+            self.rejected.store(true, Ordering::Relaxed);
+        }
+        Ok(())
+    }
+}
+
+/// Pops records from one or more Redis lists with `BLPOP`, taking from the
+/// first non-empty list in the order given. Each element is a JSON record;
+/// elements that are not are logged and dropped.
+///
+/// Elements are removed as they are read, so a record whose write fails is
+/// lost.
+pub struct RedisListReader<T: Json> {
+    connection: RedisConnection,
+    keys: Vec<String>,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Json> RedisListReader<T> {
+    pub fn builder(url: impl Into<Secret>, keys: Vec<&str>) -> RedisListReaderBuilder<T> {
+        RedisListReaderBuilder::new(url, keys)
+    }
+}
+
+/// Builds a [`RedisListReader`].
+pub struct RedisListReaderBuilder<T: Json> {
+    url: Secret,
+    keys: Vec<String>,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Json> RedisListReaderBuilder<T> {
+    fn new(url: impl Into<Secret>, keys: Vec<&str>) -> Self {
+        Self {
+            url: url.into(),
+            keys: keys.into_iter().map(String::from).collect(),
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    pub fn build(self) -> Result<RedisListReader<T>> {
+        if self.keys.is_empty() {
+            bail!("Redis list reader needs at least one key");
+        }
+        let url = self.url.resolve().context("Failed to resolve Redis URL")?;
+
+        Ok(RedisListReader {
+            connection: RedisConnection::open(&url)?,
+            keys: self.keys,
+            _marker: std::marker::PhantomData,
+        })
+    }
+}
+
+impl<T: Json> RedisListReader<T> {
+    /// Waits for an element, returning `None` if none arrived in time.
+    async fn pop(&self) -> Result<Option<(String, String)>> {
+        let mut connection = self.connection.get().await?;
+        let popped = connection
+            .blpop(&self.keys, BLOCK_TIMEOUT.as_secs_f64())
+            .await
+            .with_context(|| format!("Failed to pop from {:?}", self.keys))?;
+        Ok(popped)
+    }
+}
+
+impl<T: Json> StreamReader for RedisListReader<T> {
+    type Item = T;
+
+    async fn stream(&self) -> impl Stream<Item = Self::Item> {
+        stream! {
+            loop {
+                let (key, element) = match self.pop().await {
+                    Ok(Some(popped)) => popped,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        log::error!("{e:?}");
+                        sleep(RETRY_DELAY).await;
+                        continue;
+                    }
+                };
+                log::debug!("Popped element from '{key}'");
+
+                match serde_json::from_str::<T>(&element) {
+                    Ok(record) => yield record,
+                    Err(e) => log::error!("Dropping invalid element from '{key}': {e}"),
+                }
+            }
+        }
+    }
+}
+
+/// Receives records published to Redis channels, by name or by glob-style
+/// pattern such as `orders.*`. Each message is a JSON record; messages that
+/// are not are logged and dropped.
+///
+/// Pub/sub does not keep messages, so those published while the reader is
+/// disconnected are missed. It subscribes again after losing the connection.
+pub struct RedisPubSubReader<T: Json> {
+    connection: RedisConnection,
+    channels: Vec<String>,
+    patterns: Vec<String>,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Json> RedisPubSubReader<T> {
+    pub fn builder(url: impl Into<Secret>, channels: Vec<&str>) -> RedisPubSubReaderBuilder<T> {
+        RedisPubSubReaderBuilder::new(url, channels)
+    }
+}
+
+/// Builds a [`RedisPubSubReader`].
+pub struct RedisPubSubReaderBuilder<T: Json> {
+    url: Secret,
+    channels: Vec<String>,
+    patterns: Vec<String>,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Json> RedisPubSubReaderBuilder<T> {
+    fn new(url: impl Into<Secret>, channels: Vec<&str>) -> Self {
+        Self {
+            url: url.into(),
+            channels: channels.into_iter().map(String::from).collect(),
+            patterns: Vec::new(),
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    /// Also receives messages from every channel matching `pattern`.
+    pub fn with_pattern(mut self, pattern: &str) -> Self {
+        self.patterns.push(pattern.into());
+        self
+    }
+
+    pub fn build(self) -> Result<RedisPubSubReader<T>> {
+        if self.channels.is_empty() && self.patterns.is_empty() {
+            bail!("Redis pub/sub reader needs at least one channel or pattern");
+        }
+        let url = self.url.resolve().context("Failed to resolve Redis URL")?;
+
+        Ok(RedisPubSubReader {
+            connection: RedisConnection::open(&url)?,
+            channels: self.channels,
+            patterns: self.patterns,
+            _marker: std::marker::PhantomData,
+        })
+    }
+}
+
+impl<T: Json> RedisPubSubReader<T> {
+    async fn subscribe(&self) -> Result<redis::aio::PubSub> {
+        let mut pubsub = self
+            .connection
+            .client()
+            .get_async_pubsub()
+            .await
+            .context("Failed to connect to Redis")?;
+        if !self.channels.is_empty() {
+            pubsub
+                .subscribe(&self.channels)
+                .await
+                .with_context(|| format!("Failed to subscribe to {:?}", self.channels))?;
+        }
+        if !self.patterns.is_empty() {
+            pubsub
+                .psubscribe(&self.patterns)
+                .await
+                .with_context(|| format!("Failed to subscribe to {:?}", self.patterns))?;
+        }
+        log::info!(
+            "Subscribed to channels {:?} and patterns {:?}",
+            self.channels,
+            self.patterns
+        );
+        Ok(pubsub)
+    }
+}
+
+impl<T: Json> StreamReader for RedisPubSubReader<T> {
+    type Item = T;
+
+    async fn stream(&self) -> impl Stream<Item = Self::Item> {
+        stream! {
+            loop {
+                let pubsub = match self.subscribe().await {
+                    Ok(pubsub) => pubsub,
+                    Err(e) => {
+                        log::error!("{e:?}");
+                        sleep(RETRY_DELAY).await;
+                        continue;
+                    }
+                };
+
+                let mut messages = pubsub.into_on_message();
+                while let Some(message) = messages.next().await {
+                    let channel = message.get_channel_name();
+                    let record = message
+                        .get_payload::<String>()
+                        .context("Payload is not a string")
+                        .and_then(|payload| serde_json::from_str::<T>(&payload).context("Invalid record"));
+                    match record {
+                        Ok(record) => yield record,
+                        Err(e) => log::error!("Dropping message from '{channel}': {e:#}"),
+                    }
+                }
+
+                log::warn!("Lost connection to Redis, subscribing again");
+                sleep(RETRY_DELAY).await;
+            }
+        }
+    }
+}
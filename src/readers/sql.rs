@@ -0,0 +1,254 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use async_trait::async_trait;
+use serde_json::{Map, Value};
+use sqlx::sqlite::SqliteRow;
+use sqlx::types::Json as SqlJson;
+use sqlx::{Column, Row, TypeInfo, ValueRef};
+
+use crate::readers::Reader;
+use crate::schemas::Json;
+use crate::secret::Secret;
+use crate::sql::SqlPool;
+use crate::watermark::Watermark;
+
+/// Queries run one at a time, so a single connection is kept.
+const MAX_CONNECTIONS: u32 = 1;
+const WATERMARK_PLACEHOLDER: &str = "$watermark";
+
+/// Runs a query against PostgreSQL or SQLite on every read and returns one
+/// record per row, keyed by column name. Parameters are bound as `$1`, `$2`,
+/// and so on.
+///
+/// With a [`Watermark`], the query refers to the last watermark as
+/// `$watermark`, e.g. `WHERE updated_at > $watermark ORDER BY updated_at`.
+/// The watermark needs an initial value, e.g. `'1970-01-01T00:00:00Z'`, to
+/// compare against on the first read. PostgreSQL cannot always infer the type
+/// of a parameter, so cast it where needed, e.g. `$watermark::timestamptz`.
+pub struct SqlReader<T: Json> {
+    pool: SqlPool,
+    query: String,
+    params: Vec<Value>,
+    watermark: Option<Watermark>,
+    watermark_value: Mutex<Option<Value>>,
+    /// Watermark of the last read, saved once its rows have been written.
+    pending: Mutex<Option<Value>>,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Json> SqlReader<T> {
+    pub fn builder(url: impl Into<Secret>, query: &str) -> SqlReaderBuilder<T> {
+        SqlReaderBuilder::new(url, query)
+    }
+}
+
+/// Builds a [`SqlReader`].
+pub struct SqlReaderBuilder<T: Json> {
+    url: Secret,
+    query: String,
+    params: Vec<Value>,
+    watermark: Option<Watermark>,
+    connect_timeout: Option<Duration>,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Json> SqlReaderBuilder<T> {
+    fn new(url: impl Into<Secret>, query: &str) -> Self {
+        Self {
+            url: url.into(),
+            query: query.into(),
+            params: Vec::new(),
+            watermark: None,
+            connect_timeout: None,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    /// Binds the next positional parameter.
+    pub fn with_param(mut self, value: impl Into<Value>) -> Self {
+        self.params.push(value.into());
+        self
+    }
+
+    /// Polls incrementally, binding the largest value of the watermark
+    /// column read so far as `$watermark`. The watermark must have an
+    /// initial value.
+    pub fn with_watermark(mut self, watermark: Watermark) -> Self {
+        self.watermark = Some(watermark);
+        self
+    }
+
+    /// Maximum time to wait for a connection.
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    pub fn build(self) -> Result<SqlReader<T>> {
+        let uses_watermark = self.query.contains(WATERMARK_PLACEHOLDER);
+        match (&self.watermark, uses_watermark) {
+            (Some(_), false) => bail!("Query must use {WATERMARK_PLACEHOLDER} with a watermark"),
+            (None, true) => bail!("Query uses {WATERMARK_PLACEHOLDER} without a watermark"),
+            _ => {}
+        }
+
+        let url = self
+            .url
+            .resolve()
+            .context("Failed to resolve database URL")?;
+        let pool = SqlPool::connect_lazy(&url, MAX_CONNECTIONS, self.connect_timeout)?;
+
+        // The watermark is bound after the other parameters.
+        let query = self.query.trim().trim_end_matches(';').replace(
+            WATERMARK_PLACEHOLDER,
+            &format!("${}", self.params.len() + 1),
+        );
+        let query = match pool {
+            // PostgreSQL turns each row into JSON itself, keeping every type.
+            SqlPool::Postgres(_) => format!("SELECT to_jsonb(q) FROM ({query}) AS q"),
+            SqlPool::Sqlite(_) => query,
+        };
+
+        let watermark_value = match &self.watermark {
+            Some(watermark) => {
+                let (value, resumed) = watermark.load()?;
+                let Some(value) = value else {
+                    bail!("Query watermark needs an initial value");
+                };
+                if resumed {
+                    log::info!("Resuming query from watermark {value}");
+                }
+                Some(value)
+            }
+            None => None,
+        };
+
+        Ok(SqlReader {
+            pool,
+            query,
+            params: self.params,
+            watermark: self.watermark,
+            watermark_value: Mutex::new(watermark_value),
+            pending: Mutex::new(None),
+            _marker: std::marker::PhantomData,
+        })
+    }
+}
+
+/// Converts a SQLite row by the storage class of each value. Blobs become
+/// hex strings.
+fn sqlite_record(row: &SqliteRow) -> Result<Value> {
+    let mut record = Map::new();
+    for column in row.columns() {
+        let i = column.ordinal();
+        let raw = row.try_get_raw(i)?;
+        let value = if raw.is_null() {
+            Value::Null
+        } else {
+            match raw.type_info().name() {
+                "INTEGER" => Value::from(row.try_get_unchecked::<i64, _>(i)?),
+                "REAL" => Value::from(row.try_get_unchecked::<f64, _>(i)?),
+                "BLOB" => Value::from(hex::encode(row.try_get_unchecked::<Vec<u8>, _>(i)?)),
+                _ => Value::from(row.try_get_unchecked::<String, _>(i)?),
+            }
+        };
+        record.insert(column.name().into(), value);
+    }
+    Ok(Value::Object(record))
+}
+
+impl<T: Json> SqlReader<T> {
+    async fn fetch(&self, params: &[Value]) -> Result<Vec<Value>> {
+        match &self.pool {
+            SqlPool::Postgres(pool) => {
+                let mut query = sqlx::query_scalar::<_, SqlJson<Value>>(&self.query);
+                for param in params {
+                    query = match param {
+                        Value::Null => query.bind(None::<String>),
+                        Value::Bool(b) => query.bind(*b),
+                        Value::Number(n) => match n.as_i64() {
+                            Some(n) => query.bind(n),
+                            None => query.bind(n.as_f64()),
+                        },
+                        Value::String(s) => query.bind(s.as_str()),
+                        other => query.bind(SqlJson(other)),
+                    };
+                }
+                let rows = query.fetch_all(pool).await?;
+                Ok(rows.into_iter().map(|SqlJson(row)| row).collect())
+            }
+            SqlPool::Sqlite(pool) => {
+                let mut query = sqlx::query(&self.query);
+                for param in params {
+                    query = match param {
+                        Value::Null => query.bind(None::<String>),
+                        Value::Bool(b) => query.bind(*b),
+                        Value::Number(n) => match n.as_i64() {
+                            Some(n) => query.bind(n),
+                            None => query.bind(n.as_f64()),
+                        },
+                        Value::String(s) => query.bind(s.as_str()),
+                        other => query.bind(other.to_string()),
+                    };
+                }
+                let rows = query.fetch_all(pool).await?;
+                rows.iter().map(sqlite_record).collect()
+            }
+        }
+    }
+
+    /// Runs the query and keeps the watermark past the rows read until they
+    /// have been written.
+    async fn poll(&self) -> Result<Vec<Value>> {
+        *self.pending.lock().unwrap() = None;
+        let current = self.watermark_value.lock().unwrap().clone();
+        let mut params = self.params.clone();
+        if let Some(current) = &current {
+            params.push(current.clone());
+        }
+
+        let rows = self.fetch(&params).await.context("Failed to run query")?;
+        log::debug!("Query returned {} row(s)", rows.len());
+
+        let Some(watermark) = &self.watermark else {
+            return Ok(rows);
+        };
+        *self.pending.lock().unwrap() = watermark.next(current.as_ref(), &rows);
+        Ok(rows)
+    }
+}
+
+#[async_trait]
+impl<T: Json> Reader for SqlReader<T> {
+    type Item = T;
+
+    /// Returns every row at once, so `T` is typically a `Vec`.
+    async fn read(&self) -> Result<Option<Self::Item>> {
+        let rows = self.poll().await?;
+        let data = serde_json::from_value(Value::Array(rows))
+            .context("Failed to deserialize query result")?;
+        log::trace!("Read payload: {data:?}");
+        Ok(Some(data))
+    }
+
+    async fn read_batch(&self) -> Result<Vec<Self::Item>> {
+        self.poll()
+            .await?
+            .into_iter()
+            .map(|row| serde_json::from_value(row).context("Failed to deserialize row"))
+            .collect()
+    }
+
+    /// Saves and moves to the watermark of the last read.
+    async fn commit(&self) -> Result<()> {
+        let pending = self.pending.lock().unwrap().take();
+        if let (Some(watermark), Some(latest)) = (&self.watermark, pending) {
+            watermark.save(&latest)?;
+            log::debug!("Query watermark advanced to {latest}");
+            *self.watermark_value.lock().unwrap() = Some(latest);
+        }
+        Ok(())
+    }
+}
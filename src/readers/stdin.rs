@@ -0,0 +1,61 @@
+use async_stream::stream;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio_stream::Stream;
+
+use crate::readers::StreamReader;
+use crate::schemas::Json;
+
+/// Reads one JSON record per line from standard input, until it is closed.
+/// Lines that are not valid records are logged and skipped.
+pub struct StdinReader<T: Json> {
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Json> StdinReader<T> {
+    pub fn new() -> Self {
+        Self {
+            _marker: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<T: Json> Default for StdinReader<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Json> StreamReader for StdinReader<T> {
+    type Item = T;
+
+    fn is_bounded(&self) -> bool {
+        true
+    }
+
+    async fn stream(&self) -> impl Stream<Item = Self::Item> {
+        stream! {
+            let mut lines = BufReader::new(tokio::io::stdin()).lines();
+            let mut number = 0;
+            loop {
+                let line = match lines.next_line().await {
+                    Ok(Some(line)) => line,
+                    Ok(None) => break,
+                    Err(e) => {
+                        log::error!("Failed to read stdin: {e}");
+                        break;
+                    }
+                };
+                number += 1;
+                if line.trim().is_empty() {
+                    continue;
+                }
+
+                match serde_json::from_str::<T>(&line) {
+                    Ok(record) => yield record,
+                    Err(e) => log::error!("Skipping invalid line {number} on stdin: {e}"),
+                }
+            }
+            log::debug!("Reached end of stdin after {number} line(s)");
+        }
+    }
+}
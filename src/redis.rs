@@ -0,0 +1,48 @@
+use std::time::Duration;
+
+use ::redis::Client;
+use ::redis::aio::{ConnectionManager, ConnectionManagerConfig};
+use anyhow::{Context, Result};
+use tokio::sync::OnceCell;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long blocking reads (`BLPOP`, `XREADGROUP ... BLOCK`) wait before
+/// asking again, so that a lost connection is noticed.
+pub(crate) const BLOCK_TIMEOUT: Duration = Duration::from_secs(5);
+/// Pause after a failed command before trying again.
+pub(crate) const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// A Redis client for `redis://[user:password@]host[:port][/db]`. The
+/// connection is opened when first needed and reopened after it is lost.
+pub(crate) struct RedisConnection {
+    client: Client,
+    manager: OnceCell<ConnectionManager>,
+}
+
+impl RedisConnection {
+    pub(crate) fn open(url: &str) -> Result<Self> {
+        Ok(Self {
+            client: Client::open(url).context("Invalid Redis URL")?,
+            manager: OnceCell::new(),
+        })
+    }
+
+    pub(crate) fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Returns a handle to the shared connection. Commands sent through it
+    /// are pipelined, so it is cheap to clone.
+    pub(crate) async fn get(&self) -> Result<ConnectionManager> {
+        let manager = self
+            .manager
+            .get_or_try_init(|| {
+                let config = ConnectionManagerConfig::new().set_connection_timeout(CONNECT_TIMEOUT);
+                self.client.get_connection_manager_with_config(config)
+            })
+            .await
+            .context("Failed to connect to Redis")?;
+        Ok(manager.clone())
+    }
+}
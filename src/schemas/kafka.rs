@@ -1,6 +1,11 @@
 use std::fmt::Debug;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result, bail};
+use rdkafka::config::ClientConfig;
 
 use crate::schemas::{Json, Named};
+use crate::secret::Secret;
 
 #[derive(Debug)]
 pub struct KafkaMessage<T: Json> {
@@ -21,3 +26,157 @@ impl<T: Json> From<T> for KafkaMessage<T> {
         KafkaMessage::new(value.get_id(), value)
     }
 }
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SecurityProtocol {
+    #[default]
+    Plaintext,
+    Ssl,
+    SaslPlaintext,
+    SaslSsl,
+}
+
+impl SecurityProtocol {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SecurityProtocol::Plaintext => "PLAINTEXT",
+            SecurityProtocol::Ssl => "SSL",
+            SecurityProtocol::SaslPlaintext => "SASL_PLAINTEXT",
+            SecurityProtocol::SaslSsl => "SASL_SSL",
+        }
+    }
+
+    fn uses_sasl(&self) -> bool {
+        matches!(
+            self,
+            SecurityProtocol::SaslPlaintext | SecurityProtocol::SaslSsl
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaslMechanism {
+    Plain,
+    ScramSha256,
+    ScramSha512,
+}
+
+impl SaslMechanism {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SaslMechanism::Plain => "PLAIN",
+            SaslMechanism::ScramSha256 => "SCRAM-SHA-256",
+            SaslMechanism::ScramSha512 => "SCRAM-SHA-512",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct SaslCredentials {
+    mechanism: SaslMechanism,
+    username: Secret,
+    password: Secret,
+}
+
+/// Authentication and encryption settings shared by `KafkaReader` and
+/// `KafkaWriter`.
+#[derive(Debug, Clone, Default)]
+pub struct KafkaSecurity {
+    protocol: SecurityProtocol,
+    sasl: Option<SaslCredentials>,
+    ca_location: Option<PathBuf>,
+    certificate_location: Option<PathBuf>,
+    key_location: Option<PathBuf>,
+    key_password: Option<Secret>,
+}
+
+impl KafkaSecurity {
+    pub fn new(protocol: SecurityProtocol) -> Self {
+        Self {
+            protocol,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_sasl(
+        mut self,
+        mechanism: SaslMechanism,
+        username: impl Into<Secret>,
+        password: impl Into<Secret>,
+    ) -> Self {
+        self.sasl = Some(SaslCredentials {
+            mechanism,
+            username: username.into(),
+            password: password.into(),
+        });
+        self
+    }
+
+    pub fn with_ca_location(mut self, path: &str) -> Self {
+        self.ca_location = Some(path.into());
+        self
+    }
+
+    pub fn with_client_certificate(mut self, certificate_path: &str, key_path: &str) -> Self {
+        self.certificate_location = Some(certificate_path.into());
+        self.key_location = Some(key_path.into());
+        self
+    }
+
+    pub fn with_key_password(mut self, password: impl Into<Secret>) -> Self {
+        self.key_password = Some(password.into());
+        self
+    }
+
+    /// Checks the settings are consistent, that every referenced file exists
+    /// and that every secret can be resolved, then writes them to `config`.
+    pub fn apply(&self, config: &mut ClientConfig) -> Result<()> {
+        match (&self.sasl, self.protocol.uses_sasl()) {
+            (None, true) => bail!(
+                "Security protocol {} requires SASL credentials",
+                self.protocol.as_str()
+            ),
+            (Some(_), false) => bail!(
+                "SASL credentials were given but security protocol {} does not use SASL",
+                self.protocol.as_str()
+            ),
+            _ => {}
+        }
+
+        config.set("security.protocol", self.protocol.as_str());
+
+        if let Some(sasl) = &self.sasl {
+            let username = sasl.username.resolve().context("Invalid SASL username")?;
+            let password = sasl.password.resolve().context("Invalid SASL password")?;
+            config
+                .set("sasl.mechanism", sasl.mechanism.as_str())
+                .set("sasl.username", username)
+                .set("sasl.password", password);
+        }
+
+        let files = [
+            ("ssl.ca.location", "CA certificate", &self.ca_location),
+            (
+                "ssl.certificate.location",
+                "client certificate",
+                &self.certificate_location,
+            ),
+            ("ssl.key.location", "client key", &self.key_location),
+        ];
+        for (property, description, path) in files {
+            if let Some(path) = path {
+                if !path.is_file() {
+                    bail!("Kafka {description} not found at '{}'", path.display());
+                }
+                config.set(property, path.to_string_lossy());
+            }
+        }
+
+        if let Some(key_password) = &self.key_password {
+            let key_password = key_password.resolve().context("Invalid SSL key password")?;
+            config.set("ssl.key.password", key_password);
+        }
+
+        Ok(())
+    }
+}
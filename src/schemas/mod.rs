@@ -2,6 +2,7 @@ use std::any::type_name;
 use std::fmt::Debug;
 
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 pub mod kafka;
 
@@ -18,3 +19,17 @@ pub trait Named {
 }
 
 impl<T> Named for T {}
+
+/// Looks up a dotted path such as `data.items` or `items.0.id` in a JSON
+/// value, indexing arrays by number. An empty path returns the value itself.
+pub fn json_path<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    if path.is_empty() {
+        return Some(value);
+    }
+    path.split('.')
+        .try_fold(value, |current, segment| match current {
+            Value::Object(map) => map.get(segment),
+            Value::Array(items) => items.get(segment.parse::<usize>().ok()?),
+            _ => None,
+        })
+}
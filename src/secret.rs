@@ -0,0 +1,55 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+
+/// A sensitive value such as a password or token. It is only resolved when a
+/// client is built, so config files can point to the environment or to a
+/// mounted file instead of holding the value itself.
+#[derive(Clone)]
+pub enum Secret {
+    Value(String),
+    Env(String),
+    File(PathBuf),
+}
+
+impl Secret {
+    pub fn value(value: &str) -> Self {
+        Secret::Value(value.into())
+    }
+
+    pub fn env(name: &str) -> Self {
+        Secret::Env(name.into())
+    }
+
+    pub fn file(path: &str) -> Self {
+        Secret::File(path.into())
+    }
+
+    pub fn resolve(&self) -> Result<String> {
+        match self {
+            Secret::Value(value) => Ok(value.clone()),
+            Secret::Env(name) => std::env::var(name)
+                .with_context(|| format!("Environment variable '{name}' is not set")),
+            Secret::File(path) => std::fs::read_to_string(path)
+                .map(|content| content.trim_end().to_string())
+                .with_context(|| format!("Failed to read secret file '{}'", path.display())),
+        }
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Secret::Value(_) => write!(f, "Secret::Value(***)"),
+            Secret::Env(name) => write!(f, "Secret::Env({name:?})"),
+            Secret::File(path) => write!(f, "Secret::File({path:?})"),
+        }
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(value: &str) -> Self {
+        Secret::value(value)
+    }
+}
@@ -0,0 +1,55 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use sqlx::postgres::{PgPool, PgPoolOptions};
+use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
+
+/// A connection pool for one of the supported databases, chosen by the scheme
+/// of the URL: `postgres://` (or `postgresql://`) or `sqlite:`. Connections
+/// are opened when first needed.
+#[derive(Debug, Clone)]
+pub(crate) enum SqlPool {
+    Postgres(PgPool),
+    Sqlite(SqlitePool),
+}
+
+impl SqlPool {
+    pub(crate) fn connect_lazy(
+        url: &str,
+        max_connections: u32,
+        acquire_timeout: Option<Duration>,
+    ) -> Result<Self> {
+        if url.starts_with("postgres://") || url.starts_with("postgresql://") {
+            let mut options = PgPoolOptions::new().max_connections(max_connections);
+            if let Some(timeout) = acquire_timeout {
+                options = options.acquire_timeout(timeout);
+            }
+            let pool = options
+                .connect_lazy(url)
+                .context("Invalid PostgreSQL URL")?;
+            Ok(SqlPool::Postgres(pool))
+        } else if url.starts_with("sqlite:") {
+            let mut options = SqlitePoolOptions::new().max_connections(max_connections);
+            if let Some(timeout) = acquire_timeout {
+                options = options.acquire_timeout(timeout);
+            }
+            let pool = options.connect_lazy(url).context("Invalid SQLite URL")?;
+            Ok(SqlPool::Sqlite(pool))
+        } else {
+            bail!("Unsupported database URL, expected postgres:// or sqlite:");
+        }
+    }
+}
+
+pub(crate) fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Quotes each part of a possibly schema-qualified table name.
+pub(crate) fn quote_table(table: &str) -> String {
+    table
+        .split('.')
+        .map(quote_ident)
+        .collect::<Vec<_>>()
+        .join(".")
+}
@@ -0,0 +1,65 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+/// A small JSON file holding state that must survive restarts, such as a
+/// reader's position. Writes go to a temporary file that is renamed over the
+/// old one, so a crash never leaves a half-written state behind.
+#[derive(Debug, Clone)]
+pub struct StateFile {
+    path: PathBuf,
+}
+
+impl StateFile {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the saved state, or `None` if nothing was saved yet.
+    pub fn load<T: DeserializeOwned>(&self) -> Result<Option<T>> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(e).with_context(|| {
+                    format!("Failed to read state file '{}'", self.path.display())
+                });
+            }
+        };
+
+        let state = serde_json::from_str(&contents)
+            .with_context(|| format!("Invalid state file '{}'", self.path.display()))?;
+        Ok(Some(state))
+    }
+
+    pub fn save<T: Serialize>(&self, state: &T) -> Result<()> {
+        if let Some(parent) = self.path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            fs::create_dir_all(parent).with_context(|| {
+                format!("Failed to create state directory '{}'", parent.display())
+            })?;
+        }
+
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+
+        let contents = serde_json::to_vec_pretty(state)?;
+        fs::write(&tmp, contents)
+            .with_context(|| format!("Failed to write state file '{}'", tmp.display()))?;
+        fs::rename(&tmp, &self.path)
+            .with_context(|| format!("Failed to replace state file '{}'", self.path.display()))?;
+
+        log::trace!("Saved state to '{}'", self.path.display());
+        Ok(())
+    }
+}
@@ -0,0 +1,161 @@
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{Result, bail};
+use chrono::Utc;
+use serde_json::Value;
+
+use crate::schemas::json_path;
+
+#[derive(Debug, Clone)]
+enum Part {
+    Literal(String),
+    Env(String),
+    Now,
+    NowMillis,
+    Field(String),
+}
+
+/// A string with `{...}` placeholders that is rendered each time it is used.
+///
+/// `{env.NAME}` is replaced by an environment variable, `{now}` by the current
+/// time in RFC 3339 and `{now_millis}` by the Unix time in milliseconds. Any
+/// other placeholder is a dotted path into the values given to [`render`],
+/// e.g. `{value.device.id}`. `{{` and `}}` produce literal braces.
+///
+/// [`render`]: Template::render
+#[derive(Debug, Clone)]
+pub struct Template {
+    source: String,
+    parts: Vec<Part>,
+}
+
+impl Template {
+    pub fn parse(source: &str) -> Result<Self> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = source.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    let mut closed = false;
+                    for c in chars.by_ref() {
+                        if c == '}' {
+                            closed = true;
+                            break;
+                        }
+                        name.push(c);
+                    }
+                    if !closed {
+                        bail!("Unclosed placeholder in template '{source}'");
+                    }
+                    let name = name.trim();
+                    if name.is_empty() {
+                        bail!("Empty placeholder in template '{source}'");
+                    }
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(match name {
+                        "now" => Part::Now,
+                        "now_millis" => Part::NowMillis,
+                        _ => match name.strip_prefix("env.") {
+                            Some(var) => Part::Env(var.into()),
+                            None => Part::Field(name.into()),
+                        },
+                    });
+                }
+                '}' => bail!("Unmatched '}}' in template '{source}'"),
+                _ => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+
+        Ok(Self {
+            source: source.into(),
+            parts,
+        })
+    }
+
+    /// Whether the template looks anything up in the values it is rendered
+    /// with, rather than only using the environment and the time.
+    pub(crate) fn uses_fields(&self) -> bool {
+        self.parts.iter().any(|part| matches!(part, Part::Field(_)))
+    }
+
+    /// Renders the template, looking fields up in `values`. Missing fields and
+    /// environment variables render as an empty string.
+    pub fn render(&self, values: &Value) -> String {
+        let mut rendered = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(literal) => rendered.push_str(literal),
+                Part::Env(var) => match std::env::var(var) {
+                    Ok(value) => rendered.push_str(&value),
+                    Err(_) => log::warn!("Environment variable '{var}' is not set"),
+                },
+                Part::Now => rendered.push_str(&Utc::now().to_rfc3339()),
+                Part::NowMillis => rendered.push_str(&Utc::now().timestamp_millis().to_string()),
+                Part::Field(path) => match json_path(values, path) {
+                    Some(Value::String(value)) => rendered.push_str(value),
+                    Some(Value::Null) | None => {}
+                    Some(value) => rendered.push_str(&value.to_string()),
+                },
+            }
+        }
+        rendered
+    }
+}
+
+impl FromStr for Template {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Template::parse(s)
+    }
+}
+
+impl fmt::Display for Template {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn renders_fields_and_literal_braces() {
+        let template = Template::parse("{{{key}}}/{value.id}/{missing}").unwrap();
+        let values = json!({ "key": "k", "value": { "id": 7 } });
+        assert_eq!(template.render(&values), "{k}/7/");
+    }
+
+    #[test]
+    fn rejects_malformed_placeholders() {
+        assert!(Template::parse("devices/{value.id").is_err());
+        assert!(Template::parse("devices/{ }").is_err());
+        assert!(Template::parse("devices/}").is_err());
+    }
+
+    #[test]
+    fn tells_record_fields_from_environment_and_time() {
+        assert!(Template::parse("{value.id}").unwrap().uses_fields());
+        assert!(!Template::parse("{env.TOKEN} {now}").unwrap().uses_fields());
+    }
+}
@@ -0,0 +1,130 @@
+use std::cmp::Ordering;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::schemas::json_path;
+use crate::state::StateFile;
+
+/// Incremental polling: the largest value of `field` among the records read
+/// is kept as a watermark and passed to the next poll, so each poll only asks
+/// for new data. The watermark only moves once the records have been written.
+/// With a state file it survives restarts.
+#[derive(Debug, Clone)]
+pub struct Watermark {
+    field: String,
+    param: Option<String>,
+    initial: Option<Value>,
+    state_file: Option<StateFile>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct WatermarkState {
+    watermark: Value,
+}
+
+impl Watermark {
+    /// `field` is a dotted path inside each record, e.g. `updated_at` or `id`.
+    pub fn new(field: &str) -> Self {
+        Self {
+            field: field.into(),
+            param: None,
+            initial: None,
+            state_file: None,
+        }
+    }
+
+    /// Sends the watermark as this query parameter, e.g. `since`. Only used
+    /// by the `ApiReader`.
+    pub fn with_param(mut self, param: &str) -> Self {
+        self.param = Some(param.into());
+        self
+    }
+
+    /// Watermark used until one has been read or saved.
+    pub fn with_initial(mut self, value: impl Into<Value>) -> Self {
+        self.initial = Some(value.into());
+        self
+    }
+
+    pub fn with_state_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.state_file = Some(StateFile::new(path));
+        self
+    }
+
+    pub fn param(&self) -> Option<&String> {
+        self.param.as_ref()
+    }
+
+    /// Returns the saved watermark, or the initial one if none was saved,
+    /// along with whether it was saved.
+    pub(crate) fn load(&self) -> Result<(Option<Value>, bool)> {
+        let saved = match &self.state_file {
+            Some(state_file) => state_file
+                .load::<WatermarkState>()?
+                .map(|state| state.watermark),
+            None => None,
+        };
+        match saved {
+            Some(saved) => Ok((Some(saved), true)),
+            None => Ok((self.initial.clone(), false)),
+        }
+    }
+
+    /// Returns the largest watermark in `records` if it is past `current`.
+    /// Nothing is saved until [`save`] is called with it, once the records
+    /// have been written.
+    ///
+    /// [`save`]: Watermark::save
+    pub(crate) fn next(&self, current: Option<&Value>, records: &[Value]) -> Option<Value> {
+        let latest = self.max_in(records)?;
+        let advanced = match current {
+            Some(current) => compare_watermarks(&latest, current) == Some(Ordering::Greater),
+            None => true,
+        };
+        advanced.then_some(latest)
+    }
+
+    /// Saves `watermark` to the state file, if there is one.
+    pub(crate) fn save(&self, watermark: &Value) -> Result<()> {
+        match &self.state_file {
+            Some(state_file) => state_file.save(&WatermarkState {
+                watermark: watermark.clone(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    fn max_in(&self, records: &[Value]) -> Option<Value> {
+        records
+            .iter()
+            .filter_map(|record| json_path(record, &self.field))
+            .filter(|value| !value.is_null())
+            .fold(None, |max: Option<&Value>, value| match max {
+                Some(max) if compare_watermarks(value, max) != Some(Ordering::Greater) => Some(max),
+                _ => Some(value),
+            })
+            .cloned()
+    }
+}
+
+/// Numbers compare numerically, RFC 3339 timestamps chronologically and other
+/// strings lexicographically. Values of different kinds are not comparable.
+fn compare_watermarks(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64()?.partial_cmp(&b.as_f64()?),
+        (Value::String(a), Value::String(b)) => {
+            match (
+                DateTime::parse_from_rfc3339(a),
+                DateTime::parse_from_rfc3339(b),
+            ) {
+                (Ok(a), Ok(b)) => Some(a.cmp(&b)),
+                _ => Some(a.cmp(b)),
+            }
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,191 @@
+use anyhow::{Context, Result, bail};
+use async_trait::async_trait;
+use futures::future;
+use lapin::options::{BasicPublishOptions, ConfirmSelectOptions};
+use lapin::publisher_confirm::Confirmation;
+use lapin::{BasicProperties, Channel, Connection, ConnectionProperties};
+use serde_json::json;
+use tokio::sync::Mutex;
+
+use crate::schemas::Json;
+use crate::schemas::kafka::KafkaMessage;
+use crate::secret::Secret;
+use crate::template::Template;
+use crate::writers::Writer;
+
+/// Delivery mode of messages the broker writes to disk.
+const PERSISTENT: u8 = 2;
+
+/// Publishes each record as JSON to an AMQP 0-9-1 (RabbitMQ) exchange, with
+/// publisher confirms: a write succeeds once the broker has confirmed every
+/// message. The routing key is a [`Template`] rendered with the record as
+/// `{key}` and `{value...}`, e.g. `orders.{value.region}`.
+///
+/// The exchange `""` routes each message to the queue named by its routing
+/// key.
+pub struct AmqpWriter<T: Json> {
+    url: String,
+    exchange: String,
+    routing_key: Template,
+    mandatory: bool,
+    persistent: bool,
+    /// Opened on first use, and again after the connection is lost.
+    channel: Mutex<Option<(Connection, Channel)>>,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Json> AmqpWriter<T> {
+    pub fn builder(
+        url: impl Into<Secret>,
+        exchange: &str,
+        routing_key: &str,
+    ) -> AmqpWriterBuilder<T> {
+        AmqpWriterBuilder::new(url, exchange, routing_key)
+    }
+}
+
+/// Builds an [`AmqpWriter`]. The exchange must already exist.
+pub struct AmqpWriterBuilder<T: Json> {
+    url: Secret,
+    exchange: String,
+    routing_key: String,
+    mandatory: bool,
+    persistent: bool,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Json> AmqpWriterBuilder<T> {
+    fn new(url: impl Into<Secret>, exchange: &str, routing_key: &str) -> Self {
+        Self {
+            url: url.into(),
+            exchange: exchange.into(),
+            routing_key: routing_key.into(),
+            mandatory: false,
+            persistent: false,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    /// Fails the write when a message cannot be routed to any queue, instead
+    /// of letting the broker drop it.
+    pub fn with_mandatory(mut self) -> Self {
+        self.mandatory = true;
+        self
+    }
+
+    /// Marks messages persistent, so that durable queues keep them across
+    /// broker restarts.
+    pub fn with_persistent(mut self) -> Self {
+        self.persistent = true;
+        self
+    }
+
+    pub fn build(self) -> Result<AmqpWriter<T>> {
+        let url = self.url.resolve().context("Failed to resolve AMQP URL")?;
+        let routing_key = Template::parse(&self.routing_key).context("Invalid routing key")?;
+
+        Ok(AmqpWriter {
+            url,
+            exchange: self.exchange,
+            routing_key,
+            mandatory: self.mandatory,
+            persistent: self.persistent,
+            channel: Mutex::new(None),
+            _marker: std::marker::PhantomData,
+        })
+    }
+}
+
+impl<T: Json> AmqpWriter<T> {
+    /// Returns a channel in confirm mode, connecting if there is none.
+    async fn channel(&self) -> Result<Channel> {
+        let mut open = self.channel.lock().await;
+        if let Some((_, channel)) = open.as_ref()
+            && channel.status().connected()
+        {
+            return Ok(channel.clone());
+        }
+
+        let connection = Connection::connect(&self.url, ConnectionProperties::default())
+            .await
+            .context("Failed to connect to AMQP broker")?;
+        let channel = connection.create_channel().await?;
+        channel
+            .confirm_select(ConfirmSelectOptions::default())
+            .await
+            .context("Failed to enable publisher confirms")?;
+        log::info!("Connected to AMQP broker");
+
+        *open = Some((connection, channel.clone()));
+        Ok(channel)
+    }
+
+    fn properties(&self) -> BasicProperties {
+        let properties = BasicProperties::default().with_content_type("application/json".into());
+        match self.persistent {
+            true => properties.with_delivery_mode(PERSISTENT),
+            false => properties,
+        }
+    }
+}
+
+#[async_trait]
+impl<T: Json> Writer for AmqpWriter<T> {
+    type Item = KafkaMessage<T>;
+
+    async fn write(&self, data: KafkaMessage<T>) -> Result<()> {
+        self.write_batch(vec![data]).await
+    }
+
+    /// Publishes every record, then waits for all the confirms at once.
+    async fn write_batch(&self, data: Vec<KafkaMessage<T>>) -> Result<()> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        let channel = self.channel().await?;
+        let options = BasicPublishOptions {
+            mandatory: self.mandatory,
+            ..Default::default()
+        };
+
+        let mut confirms = Vec::with_capacity(data.len());
+        for message in &data {
+            let payload = serde_json::to_vec(&message.value)?;
+            let routing_key = self
+                .routing_key
+                .render(&json!({ "key": message.key, "value": message.value }));
+            let confirm = channel
+                .basic_publish(
+                    &self.exchange,
+                    &routing_key,
+                    options,
+                    &payload,
+                    self.properties(),
+                )
+                .await
+                .with_context(|| format!("Failed to publish to exchange '{}'", self.exchange))?;
+            confirms.push(confirm);
+        }
+
+        for confirmation in future::try_join_all(confirms).await? {
+            match confirmation {
+                Confirmation::Ack(None) | Confirmation::NotRequested => {}
+                Confirmation::Ack(Some(returned)) => bail!(
+                    "Message to exchange '{}' was returned: {}",
+                    self.exchange,
+                    returned.reply_text
+                ),
+                Confirmation::Nack(_) => {
+                    bail!("Broker rejected a message to exchange '{}'", self.exchange)
+                }
+            }
+        }
+
+        log::debug!(
+            "Published {} record(s) to exchange '{}'",
+            data.len(),
+            self.exchange
+        );
+        Ok(())
+    }
+}
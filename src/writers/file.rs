@@ -0,0 +1,348 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, bail};
+use async_trait::async_trait;
+use chrono::Utc;
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use serde_json::Value;
+use tokio::fs::{File, OpenOptions};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::Mutex;
+
+use crate::format::Format;
+use crate::schemas::Json;
+use crate::schemas::kafka::KafkaMessage;
+use crate::writers::Writer;
+
+/// Appends records to a file as NDJSON, CSV or text lines. The key of a
+/// record is not written.
+///
+/// With a size or age limit the file is rotated: it is renamed with a
+/// timestamp, e.g. `events-20250101T120000.000.jsonl`, optionally gzipped,
+/// and a new file is started at the original path.
+pub struct FileWriter<T: Json> {
+    path: PathBuf,
+    format: Format,
+    columns: Option<Vec<String>>,
+    max_bytes: Option<u64>,
+    max_age: Option<Duration>,
+    gzip: bool,
+    current: Mutex<Option<OpenFile>>,
+    _marker: std::marker::PhantomData<T>,
+}
+
+struct OpenFile {
+    file: File,
+    size: u64,
+    opened_at: Instant,
+    /// CSV columns, taken from the existing header or the first record when
+    /// none are configured.
+    columns: Option<Vec<String>>,
+}
+
+impl<T: Json> FileWriter<T> {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self::builder(path).build().expect("Invalid file writer")
+    }
+
+    pub fn builder(path: impl Into<PathBuf>) -> FileWriterBuilder<T> {
+        FileWriterBuilder::new(path)
+    }
+}
+
+/// Builds a [`FileWriter`]. Records are written as NDJSON unless another
+/// format is set.
+pub struct FileWriterBuilder<T: Json> {
+    path: PathBuf,
+    format: Format,
+    columns: Option<Vec<String>>,
+    max_bytes: Option<u64>,
+    max_age: Option<Duration>,
+    gzip: bool,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Json> FileWriterBuilder<T> {
+    fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            format: Format::Ndjson,
+            columns: None,
+            max_bytes: None,
+            max_age: None,
+            gzip: false,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    pub fn with_format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// CSV columns, in order. Defaults to the header of an existing file or
+    /// the fields of the first record.
+    pub fn with_columns(mut self, columns: Vec<String>) -> Self {
+        self.columns = Some(columns);
+        self
+    }
+
+    /// Rotates the file once it holds at least this many bytes.
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Rotates the file once it has been written to for this long. The age is
+    /// checked when the next record is written, so a file that receives
+    /// nothing more stays open, and is not rotated, until one arrives.
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Gzips rotated files.
+    pub fn with_gzip(mut self) -> Self {
+        self.gzip = true;
+        self
+    }
+
+    pub fn build(self) -> Result<FileWriter<T>> {
+        if !matches!(self.format, Format::Ndjson | Format::Csv | Format::Text) {
+            bail!(
+                "Cannot write '{}' as {}, expected ndjson, csv or text",
+                self.path.display(),
+                self.format
+            );
+        }
+
+        Ok(FileWriter {
+            path: self.path,
+            format: self.format,
+            columns: self.columns,
+            max_bytes: self.max_bytes,
+            max_age: self.max_age,
+            gzip: self.gzip,
+            current: Mutex::new(None),
+            _marker: std::marker::PhantomData,
+        })
+    }
+}
+
+impl<T: Json> FileWriter<T> {
+    async fn open(&self) -> Result<OpenFile> {
+        if let Some(parent) = self.path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .with_context(|| format!("Failed to create directory '{}'", parent.display()))?;
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .with_context(|| format!("Failed to open '{}'", self.path.display()))?;
+        let size = file.metadata().await?.len();
+
+        let columns = match (&self.columns, self.format) {
+            (Some(columns), _) => Some(columns.clone()),
+            (None, Format::Csv) if size > 0 => Some(read_csv_header(&self.path).await?),
+            _ => None,
+        };
+        log::debug!("Opened '{}' ({size} bytes)", self.path.display());
+
+        Ok(OpenFile {
+            file,
+            size,
+            opened_at: Instant::now(),
+            columns,
+        })
+    }
+
+    fn needs_rotation(&self, current: &OpenFile) -> bool {
+        current.size > 0
+            && (self.max_bytes.is_some_and(|max| current.size >= max)
+                || self
+                    .max_age
+                    .is_some_and(|max| current.opened_at.elapsed() >= max))
+    }
+
+    /// Moves the current file aside, compressing it if configured.
+    async fn rotate(&self, current: OpenFile) -> Result<()> {
+        let mut file = current.file;
+        file.flush().await?;
+        drop(file);
+
+        let rotated = rotated_path(&self.path);
+        tokio::fs::rename(&self.path, &rotated)
+            .await
+            .with_context(|| format!("Failed to rotate '{}'", self.path.display()))?;
+        log::info!(
+            "Rotated '{}' to '{}'",
+            self.path.display(),
+            rotated.display()
+        );
+
+        if self.gzip {
+            tokio::task::spawn_blocking(move || gzip(&rotated)).await??;
+        }
+        Ok(())
+    }
+
+    fn encode(&self, columns: &mut Option<Vec<String>>, values: &[Value]) -> Result<Vec<u8>> {
+        let mut out = Vec::new();
+        match self.format {
+            Format::Csv => {
+                let mut writer = csv::Writer::from_writer(&mut out);
+                if columns.is_none() {
+                    let first = values.first().and_then(Value::as_object);
+                    let header: Vec<String> = first
+                        .map(|record| record.keys().cloned().collect())
+                        .unwrap_or_default();
+                    writer.write_record(&header)?;
+                    *columns = Some(header);
+                }
+                let columns = columns.as_deref().unwrap_or_default();
+                for value in values {
+                    writer.write_record(columns.iter().map(|column| match value.get(column) {
+                        Some(Value::String(s)) => s.clone(),
+                        Some(Value::Null) | None => String::new(),
+                        Some(other) => other.to_string(),
+                    }))?;
+                }
+                writer.flush()?;
+            }
+            Format::Text => {
+                for value in values {
+                    match value {
+                        Value::String(s) => out.extend_from_slice(s.as_bytes()),
+                        other => out.extend_from_slice(other.to_string().as_bytes()),
+                    }
+                    out.push(b'\n');
+                }
+            }
+            _ => {
+                for value in values {
+                    serde_json::to_writer(&mut out, value)?;
+                    out.push(b'\n');
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    async fn append(&self, values: Vec<Value>) -> Result<()> {
+        let mut current = self.current.lock().await;
+
+        if let Some(open) = current.take() {
+            if self.needs_rotation(&open) {
+                self.rotate(open).await?;
+            } else {
+                *current = Some(open);
+            }
+        }
+        let open = match current.as_mut() {
+            Some(open) => open,
+            None => current.insert(self.open().await?),
+        };
+
+        // A new CSV file starts with its header row.
+        if open.size == 0 && self.format == Format::Csv {
+            open.columns = self.columns.clone();
+            if let Some(columns) = &open.columns {
+                let mut writer = csv::Writer::from_writer(Vec::new());
+                writer.write_record(columns)?;
+                let header = writer.into_inner()?;
+                open.file.write_all(&header).await?;
+                open.size += header.len() as u64;
+            }
+        }
+
+        let bytes = self.encode(&mut open.columns, &values)?;
+        open.file
+            .write_all(&bytes)
+            .await
+            .with_context(|| format!("Failed to write to '{}'", self.path.display()))?;
+        open.file.flush().await?;
+        open.size += bytes.len() as u64;
+        log::debug!(
+            "Wrote {} record(s) to '{}'",
+            values.len(),
+            self.path.display()
+        );
+        Ok(())
+    }
+}
+
+async fn read_csv_header(path: &Path) -> Result<Vec<String>> {
+    let file = File::open(path).await?;
+    let mut line = String::new();
+    BufReader::new(file).read_line(&mut line).await?;
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .from_reader(line.as_bytes());
+    let header = reader
+        .records()
+        .next()
+        .transpose()?
+        .with_context(|| format!("No CSV header in '{}'", path.display()))?;
+    Ok(header.iter().map(String::from).collect())
+}
+
+/// A free name for a rotated file, adding a counter when several rotations
+/// happen within the same millisecond.
+fn rotated_path(path: &Path) -> PathBuf {
+    let timestamp = Utc::now().format("%Y%m%dT%H%M%S%.3f");
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+
+    (0..)
+        .map(|n| match n {
+            0 => format!("{stem}-{timestamp}{extension}"),
+            n => format!("{stem}-{timestamp}-{n}{extension}"),
+        })
+        .map(|name| path.with_file_name(name))
+        .find(|rotated| !rotated.exists() && !rotated.with_added_extension("gz").exists())
+        .expect("No free name for rotated file")
+}
+
+fn gzip(path: &Path) -> Result<()> {
+    let gz_path = path.with_added_extension("gz");
+    let mut input = std::fs::File::open(path)?;
+    let output = std::fs::File::create(&gz_path)?;
+    let mut encoder = GzEncoder::new(output, Compression::default());
+    std::io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?;
+    std::fs::remove_file(path)?;
+
+    log::debug!("Compressed '{}'", path.display());
+    Ok(())
+}
+
+#[async_trait]
+impl<T: Json> Writer for FileWriter<T> {
+    type Item = KafkaMessage<T>;
+
+    async fn write(&self, data: KafkaMessage<T>) -> Result<()> {
+        self.append(vec![serde_json::to_value(&data.value)?]).await
+    }
+
+    async fn write_batch(&self, data: Vec<KafkaMessage<T>>) -> Result<()> {
+        let values = data
+            .into_iter()
+            .map(|message| serde_json::to_value(message.value))
+            .collect::<serde_json::Result<_>>()?;
+        self.append(values).await
+    }
+}
@@ -0,0 +1,250 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow, bail};
+use async_trait::async_trait;
+use reqwest::Client;
+pub use reqwest::Method;
+use reqwest::header::CONTENT_TYPE;
+use serde_json::{Value, json};
+
+use crate::auth::HttpAuth;
+use crate::http;
+use crate::schemas::Json;
+use crate::schemas::kafka::KafkaMessage;
+use crate::template::Template;
+use crate::writers::Writer;
+
+const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// How `write_batch` sends several records in one request.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BatchFormat {
+    /// One request per record, like `write`.
+    #[default]
+    Individual,
+    /// A single JSON array.
+    JsonArray,
+    /// One JSON document per line (`application/x-ndjson`).
+    Ndjson,
+}
+
+/// Sends records to an HTTP endpoint, one JSON body per record. The key of a
+/// record is not sent, but header values are [`Template`]s rendered with the
+/// record as `{key}` and `{value...}`. A batch sent as one request has no
+/// single record, so its headers may only use `{env...}`, `{now}` and
+/// `{now_millis}`.
+pub struct HttpWriter<T: Json> {
+    client: Client,
+    url: String,
+    method: Method,
+    headers: Vec<(String, Template)>,
+    auth: Option<HttpAuth>,
+    batch_format: BatchFormat,
+    max_retries: u32,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Json> HttpWriter<T> {
+    pub fn new(url: &str) -> Self {
+        Self::builder(url)
+            .build()
+            .expect("HTTP client creation failed")
+    }
+
+    pub fn builder(url: &str) -> HttpWriterBuilder<T> {
+        HttpWriterBuilder::new(url)
+    }
+}
+
+/// Builds an [`HttpWriter`]. Records are POSTed unless another method is set.
+pub struct HttpWriterBuilder<T: Json> {
+    url: String,
+    method: Method,
+    headers: Vec<(String, String)>,
+    auth: Option<HttpAuth>,
+    batch_format: BatchFormat,
+    max_retries: u32,
+    connect_timeout: Option<Duration>,
+    timeout: Option<Duration>,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Json> HttpWriterBuilder<T> {
+    fn new(url: &str) -> Self {
+        Self {
+            url: url.into(),
+            method: Method::POST,
+            headers: Vec::new(),
+            auth: None,
+            batch_format: BatchFormat::default(),
+            max_retries: DEFAULT_MAX_RETRIES,
+            connect_timeout: None,
+            timeout: None,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    pub fn with_method(mut self, method: Method) -> Self {
+        self.method = method;
+        self
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn with_auth(mut self, auth: HttpAuth) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// Only used by `write_batch`, so it has no effect on a stream operation
+    /// or an interval operation writing records one at a time.
+    pub fn with_batch_format(mut self, batch_format: BatchFormat) -> Self {
+        self.batch_format = batch_format;
+        self
+    }
+
+    /// Retries of a request answered with `429 Too Many Requests` or a `5xx`
+    /// status. Defaults to 3.
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Maximum time for each request, from connecting to reading the end of
+    /// the response.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn build(self) -> Result<HttpWriter<T>> {
+        if let Some(auth) = &self.auth {
+            auth.validate()
+                .with_context(|| format!("Invalid authentication for {}", self.url))?;
+        }
+
+        let mut client = Client::builder();
+        if let Some(timeout) = self.connect_timeout {
+            client = client.connect_timeout(timeout);
+        }
+        if let Some(timeout) = self.timeout {
+            client = client.timeout(timeout);
+        }
+        let client = client
+            .build()
+            .with_context(|| format!("Failed to create HTTP client for {}", self.url))?;
+
+        let headers: Vec<(String, Template)> = self
+            .headers
+            .into_iter()
+            .map(|(name, value)| Ok((name, Template::parse(&value)?)))
+            .collect::<Result<_>>()?;
+        if self.batch_format != BatchFormat::Individual
+            && let Some((name, _)) = headers.iter().find(|(_, value)| value.uses_fields())
+        {
+            bail!(
+                "Header '{name}' of {} uses record fields, which are not available when a batch is sent as one request",
+                self.url
+            );
+        }
+
+        Ok(HttpWriter {
+            client,
+            url: self.url,
+            method: self.method,
+            headers,
+            auth: self.auth,
+            batch_format: self.batch_format,
+            max_retries: self.max_retries,
+            _marker: std::marker::PhantomData,
+        })
+    }
+}
+
+impl<T: Json> HttpWriter<T> {
+    /// Sends `body`, retrying while the server is throttling or failing.
+    async fn send(&self, body: String, content_type: &str, values: &Value) -> Result<()> {
+        let request = || async {
+            let mut request = self
+                .client
+                .request(self.method.clone(), &self.url)
+                .header(CONTENT_TYPE, content_type);
+            for (name, value) in &self.headers {
+                request = request.header(name, value.render(values));
+            }
+            if let Some(auth) = &self.auth {
+                request = auth.apply(&self.client, request).await?;
+            }
+            Ok(request.body(body.clone()))
+        };
+        let response = http::send(
+            &self.url,
+            self.max_retries,
+            None,
+            self.auth.as_ref(),
+            request,
+        )
+        .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            log::error!("HTTP error {status}: {}", self.url);
+            return Err(anyhow!("HTTP error: {status}"));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<T: Json> Writer for HttpWriter<T> {
+    type Item = KafkaMessage<T>;
+
+    async fn write(&self, data: KafkaMessage<T>) -> Result<()> {
+        log::debug!("Sending record to {}", self.url);
+
+        let value = serde_json::to_value(&data.value)?;
+        let body = value.to_string();
+        log::trace!("Serialized payload for {}: {body:?}", self.url);
+
+        let values = json!({ "key": data.key, "value": value });
+        self.send(body, "application/json", &values).await?;
+        log::debug!("Delivered record to {}", self.url);
+        Ok(())
+    }
+
+    async fn write_batch(&self, data: Vec<KafkaMessage<T>>) -> Result<()> {
+        let total = data.len();
+        let values = |data: Vec<KafkaMessage<T>>| {
+            data.into_iter()
+                .map(|message| serde_json::to_value(message.value))
+                .collect::<serde_json::Result<Vec<_>>>()
+        };
+
+        let (body, content_type) = match self.batch_format {
+            BatchFormat::Individual => {
+                for message in data {
+                    self.write(message).await?;
+                }
+                return Ok(());
+            }
+            BatchFormat::JsonArray => (Value::Array(values(data)?).to_string(), "application/json"),
+            BatchFormat::Ndjson => {
+                let lines: Vec<String> = values(data)?.iter().map(Value::to_string).collect();
+                (lines.join("\n") + "\n", "application/x-ndjson")
+            }
+        };
+
+        log::debug!("Sending {total} record(s) to {}", self.url);
+        self.send(body, content_type, &json!({})).await?;
+        log::debug!("Delivered {total} record(s) to {}", self.url);
+        Ok(())
+    }
+}
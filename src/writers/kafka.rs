@@ -1,12 +1,13 @@
 use std::time::Duration;
 
-use anyhow::Result;
+use anyhow::{Context, Result, anyhow};
 use async_trait::async_trait;
+use futures::future;
 use rdkafka::config::ClientConfig;
 use rdkafka::producer::{FutureProducer, FutureRecord};
 
 use crate::schemas::Json;
-use crate::schemas::kafka::KafkaMessage;
+use crate::schemas::kafka::{KafkaMessage, KafkaSecurity};
 use crate::writers::Writer;
 
 pub struct KafkaWriter<T: Json> {
@@ -17,18 +18,85 @@ pub struct KafkaWriter<T: Json> {
 
 impl<T: Json> KafkaWriter<T> {
     pub fn new(brokers: &str, topic: &str) -> Self {
-        let producer: FutureProducer = ClientConfig::new()
+        Self::try_new(brokers, topic).expect("Kafka Producer creation failed")
+    }
+
+    pub fn try_new(brokers: &str, topic: &str) -> Result<Self> {
+        Self::builder(brokers, topic).build()
+    }
+
+    pub fn builder(brokers: &str, topic: &str) -> KafkaWriterBuilder<T> {
+        KafkaWriterBuilder::new(brokers, topic)
+    }
+}
+
+/// Builds a [`KafkaWriter`], letting any librdkafka producer property be set
+/// on top of (or in place of) courier's defaults.
+pub struct KafkaWriterBuilder<T: Json> {
+    config: ClientConfig,
+    topic: String,
+    security: Option<KafkaSecurity>,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Json> KafkaWriterBuilder<T> {
+    fn new(brokers: &str, topic: &str) -> Self {
+        let mut config = ClientConfig::new();
+        config
             .set("bootstrap.servers", brokers)
-            .set("message.timeout.ms", "5000")
-            .create()
-            .unwrap();
+            .set("message.timeout.ms", "5000");
 
         Self {
-            producer,
+            config,
             topic: topic.into(),
+            security: None,
             _marker: std::marker::PhantomData,
         }
     }
+
+    /// Sets a librdkafka property, overriding the default if there is one.
+    pub fn with_property(mut self, key: &str, value: &str) -> Self {
+        self.config.set(key, value);
+        self
+    }
+
+    pub fn with_properties<K, V>(mut self, properties: impl IntoIterator<Item = (K, V)>) -> Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        for (key, value) in properties {
+            self.config.set(key, value);
+        }
+        self
+    }
+
+    pub fn with_security(mut self, security: KafkaSecurity) -> Self {
+        self.security = Some(security);
+        self
+    }
+
+    pub fn build(mut self) -> Result<KafkaWriter<T>> {
+        if let Some(security) = &self.security {
+            security
+                .apply(&mut self.config)
+                .context("Invalid Kafka security configuration")?;
+        }
+
+        let producer: FutureProducer = self.config.create().with_context(|| {
+            format!(
+                "Failed to create Kafka producer for topic '{}' on brokers '{}'",
+                self.topic,
+                self.config.get("bootstrap.servers").unwrap_or_default()
+            )
+        })?;
+
+        Ok(KafkaWriter {
+            producer,
+            topic: self.topic,
+            _marker: std::marker::PhantomData,
+        })
+    }
 }
 
 #[async_trait]
@@ -73,4 +141,23 @@ impl<T: Json> Writer for KafkaWriter<T> {
             }
         }
     }
+
+    async fn write_batch(&self, data: Vec<KafkaMessage<T>>) -> Result<()> {
+        let total = data.len();
+        log::debug!("Sending {total} message(s) to topic: {}", self.topic);
+
+        // The producer batches in-flight records itself, so every record is
+        // handed over before waiting on any delivery.
+        let results = future::join_all(data.into_iter().map(|item| self.write(item))).await;
+        let failed = results.iter().filter(|result| result.is_err()).count();
+        if failed > 0 {
+            return Err(anyhow!(
+                "Failed to deliver {failed} of {total} message(s) to topic '{}'",
+                self.topic
+            ));
+        }
+
+        log::debug!("Delivered {total} message(s) to topic '{}'", self.topic);
+        Ok(())
+    }
 }
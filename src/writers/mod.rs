@@ -3,7 +3,16 @@ use std::any::type_name;
 use anyhow::Result;
 use async_trait::async_trait;
 
+pub mod amqp;
+pub mod file;
+pub mod http;
 pub mod kafka;
+pub mod mqtt;
+pub mod nats;
+pub mod postgres;
+pub mod redis;
+pub mod sqlite;
+pub mod stdout;
 
 #[async_trait]
 pub trait Writer: Sync + Send {
@@ -11,6 +20,15 @@ pub trait Writer: Sync + Send {
 
     async fn write(&self, data: Self::Item) -> Result<()>;
 
+    /// Writes several records at once. Writers that can send records
+    /// concurrently override this; by default each record is written in turn.
+    async fn write_batch(&self, data: Vec<Self::Item>) -> Result<()> {
+        for item in data {
+            self.write(item).await?;
+        }
+        Ok(())
+    }
+
     fn set_id(&mut self, _id: &str) {}
 
     fn get_id(&self) -> &'static str {
@@ -0,0 +1,195 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use async_trait::async_trait;
+use rumqttc::{AsyncClient, Event, MqttOptions, Outgoing, Packet};
+use serde_json::json;
+use tokio::sync::mpsc::{UnboundedReceiver, unbounded_channel};
+use tokio::sync::{Mutex, OnceCell};
+use tokio::time::sleep;
+
+use crate::mqtt::{EventLoopTask, MqttSettings, QoS, RETRY_DELAY, client};
+use crate::schemas::Json;
+use crate::schemas::kafka::KafkaMessage;
+use crate::secret::Secret;
+use crate::template::Template;
+use crate::writers::Writer;
+
+/// Publishes each record as JSON to an MQTT topic. The topic is a
+/// [`Template`] rendered with the record as `{key}` and `{value...}`, e.g.
+/// `devices/{value.device_id}/commands`.
+///
+/// With QoS 1 or 2, a write returns once the broker has acknowledged the
+/// message, which the client sends again after a reconnect until it has. With
+/// QoS 0, it returns once the message is queued.
+pub struct MqttWriter<T: Json> {
+    options: MqttOptions,
+    topic: Template,
+    qos: QoS,
+    retain: bool,
+    connection: OnceCell<Connection>,
+    _marker: std::marker::PhantomData<T>,
+}
+
+/// What the event loop reports about messages published with QoS 1 or 2.
+enum Notice {
+    /// A message was sent, or sent again, with this packet ID.
+    Sent(u16),
+    /// The broker acknowledged the message with this packet ID.
+    Acknowledged(u16),
+}
+
+struct Connection {
+    client: AsyncClient,
+    /// Held while a write waits for its acknowledgement, so that only one
+    /// message is in flight.
+    notices: Mutex<UnboundedReceiver<Notice>>,
+    _task: EventLoopTask,
+}
+
+impl<T: Json> MqttWriter<T> {
+    pub fn builder(broker: &str, client_id: &str, topic: &str) -> MqttWriterBuilder<T> {
+        MqttWriterBuilder::new(broker, client_id, topic)
+    }
+}
+
+/// Builds an [`MqttWriter`]. Messages are published with QoS 1 and not
+/// retained unless configured otherwise.
+pub struct MqttWriterBuilder<T: Json> {
+    settings: MqttSettings,
+    topic: String,
+    qos: QoS,
+    retain: bool,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Json> MqttWriterBuilder<T> {
+    fn new(broker: &str, client_id: &str, topic: &str) -> Self {
+        Self {
+            settings: MqttSettings::new(broker, client_id),
+            topic: topic.into(),
+            qos: QoS::AtLeastOnce,
+            retain: false,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    pub fn with_qos(mut self, qos: QoS) -> Self {
+        self.qos = qos;
+        self
+    }
+
+    /// Has the broker keep the last message of each topic for new
+    /// subscribers.
+    pub fn with_retain(mut self) -> Self {
+        self.retain = true;
+        self
+    }
+
+    pub fn with_credentials(mut self, username: &str, password: impl Into<Secret>) -> Self {
+        self.settings.set_credentials(username, password.into());
+        self
+    }
+
+    pub fn with_keep_alive(mut self, keep_alive: Duration) -> Self {
+        self.settings.set_keep_alive(keep_alive);
+        self
+    }
+
+    /// Asks the broker to keep the session when disconnected, instead of
+    /// starting a clean one.
+    pub fn with_persistent_session(mut self) -> Self {
+        self.settings.set_persistent_session();
+        self
+    }
+
+    pub fn build(self) -> Result<MqttWriter<T>> {
+        if self.topic.is_empty() {
+            bail!("MQTT writer needs a topic");
+        }
+        let topic = Template::parse(&self.topic).context("Invalid MQTT topic")?;
+
+        Ok(MqttWriter {
+            options: self.settings.options()?,
+            topic,
+            qos: self.qos,
+            retain: self.retain,
+            connection: OnceCell::new(),
+            _marker: std::marker::PhantomData,
+        })
+    }
+}
+
+impl<T: Json> MqttWriter<T> {
+    /// Returns the connection, connecting on first use.
+    async fn connection(&self) -> &Connection {
+        self.connection
+            .get_or_init(|| async {
+                let (client, mut event_loop) = client(self.options.clone());
+                let (sender, receiver) = unbounded_channel();
+                let acknowledged = self.qos != QoS::AtMostOnce;
+                let task = tokio::spawn(async move {
+                    loop {
+                        let notice = match event_loop.poll().await {
+                            Ok(Event::Outgoing(Outgoing::Publish(pkid))) => Notice::Sent(pkid),
+                            Ok(Event::Incoming(Packet::PubAck(ack))) => {
+                                Notice::Acknowledged(ack.pkid)
+                            }
+                            Ok(Event::Incoming(Packet::PubComp(comp))) => {
+                                Notice::Acknowledged(comp.pkid)
+                            }
+                            Ok(_) => continue,
+                            Err(e) => {
+                                log::error!("MQTT connection error: {e}");
+                                sleep(RETRY_DELAY).await;
+                                continue;
+                            }
+                        };
+                        if acknowledged {
+                            let _ = sender.send(notice);
+                        }
+                    }
+                });
+                Connection {
+                    client,
+                    notices: Mutex::new(receiver),
+                    _task: EventLoopTask(task),
+                }
+            })
+            .await
+    }
+}
+
+#[async_trait]
+impl<T: Json> Writer for MqttWriter<T> {
+    type Item = KafkaMessage<T>;
+
+    async fn write(&self, data: KafkaMessage<T>) -> Result<()> {
+        let payload = serde_json::to_vec(&data.value)?;
+        let topic = self
+            .topic
+            .render(&json!({ "key": data.key, "value": data.value }));
+
+        let connection = self.connection().await;
+        let mut notices = connection.notices.lock().await;
+        connection
+            .client
+            .publish(&topic, self.qos, self.retain, payload)
+            .await
+            .with_context(|| format!("Failed to publish to '{topic}'"))?;
+
+        if self.qos != QoS::AtMostOnce {
+            let mut sent = None;
+            loop {
+                match notices.recv().await {
+                    Some(Notice::Sent(pkid)) => sent = Some(pkid),
+                    Some(Notice::Acknowledged(pkid)) if sent == Some(pkid) => break,
+                    Some(Notice::Acknowledged(_)) => {}
+                    None => bail!("MQTT connection closed before '{topic}' was acknowledged"),
+                }
+            }
+        }
+        log::debug!("Published record to '{topic}'");
+        Ok(())
+    }
+}
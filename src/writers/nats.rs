@@ -0,0 +1,148 @@
+use anyhow::{Context, Result};
+use async_nats::Client;
+use async_nats::jetstream;
+use async_trait::async_trait;
+use futures::future;
+use serde_json::json;
+use tokio::sync::OnceCell;
+
+use crate::nats::{NatsConnection, NatsSettings};
+use crate::schemas::Json;
+use crate::schemas::kafka::KafkaMessage;
+use crate::secret::Secret;
+use crate::template::Template;
+use crate::writers::Writer;
+
+/// Publishes each record as JSON to a NATS subject. The subject is a
+/// [`Template`] rendered with the record as `{key}` and `{value...}`, e.g.
+/// `orders.{value.region}`.
+///
+/// With core NATS, a write returns once the messages have been flushed to the
+/// server. With JetStream, it returns once a stream has stored every message.
+pub struct NatsWriter<T: Json> {
+    connection: NatsConnection,
+    subject: Template,
+    jetstream: bool,
+    /// Connected on first use; the client reconnects by itself after that.
+    client: OnceCell<Client>,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Json> NatsWriter<T> {
+    pub fn builder(url: impl Into<Secret>, subject: &str) -> NatsWriterBuilder<T> {
+        NatsWriterBuilder::new(url, subject)
+    }
+}
+
+/// Builds a [`NatsWriter`], publishing with core NATS unless configured to
+/// use JetStream.
+pub struct NatsWriterBuilder<T: Json> {
+    settings: NatsSettings,
+    subject: String,
+    jetstream: bool,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Json> NatsWriterBuilder<T> {
+    fn new(url: impl Into<Secret>, subject: &str) -> Self {
+        Self {
+            settings: NatsSettings::new(url.into()),
+            subject: subject.into(),
+            jetstream: false,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    /// Authenticates with a `.creds` file holding a user JWT and NKey seed.
+    pub fn with_credentials_file(mut self, path: &str) -> Self {
+        self.settings.set_credentials_file(path);
+        self
+    }
+
+    /// Publishes through JetStream, waiting for the stream to acknowledge
+    /// each message. A stream must capture the subjects written to.
+    pub fn with_jetstream(mut self) -> Self {
+        self.jetstream = true;
+        self
+    }
+
+    pub fn build(self) -> Result<NatsWriter<T>> {
+        let subject = Template::parse(&self.subject).context("Invalid subject")?;
+
+        Ok(NatsWriter {
+            connection: self.settings.resolve()?,
+            subject,
+            jetstream: self.jetstream,
+            client: OnceCell::new(),
+            _marker: std::marker::PhantomData,
+        })
+    }
+}
+
+impl<T: Json> NatsWriter<T> {
+    async fn client(&self) -> Result<&Client> {
+        self.client
+            .get_or_try_init(|| self.connection.connect())
+            .await
+    }
+
+    /// Renders each record's subject and serializes its value.
+    fn messages(&self, data: &[KafkaMessage<T>]) -> Result<Vec<(String, Vec<u8>)>> {
+        data.iter()
+            .map(|message| {
+                let subject = self
+                    .subject
+                    .render(&json!({ "key": message.key, "value": message.value }));
+                Ok((subject, serde_json::to_vec(&message.value)?))
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl<T: Json> Writer for NatsWriter<T> {
+    type Item = KafkaMessage<T>;
+
+    async fn write(&self, data: KafkaMessage<T>) -> Result<()> {
+        self.write_batch(vec![data]).await
+    }
+
+    /// Publishes every record, then waits for the flush or for all the
+    /// JetStream acknowledgements at once.
+    async fn write_batch(&self, data: Vec<KafkaMessage<T>>) -> Result<()> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        let messages = self.messages(&data)?;
+        let client = self.client().await?;
+
+        if self.jetstream {
+            let context = jetstream::new(client.clone());
+            let mut acks = Vec::with_capacity(messages.len());
+            for (subject, payload) in messages {
+                let ack = context
+                    .publish(subject.clone(), payload.into())
+                    .await
+                    .with_context(|| format!("Failed to publish to '{subject}'"))?;
+                acks.push(ack.into_future());
+            }
+            future::try_join_all(acks)
+                .await
+                .context("JetStream did not store a message")?;
+        } else {
+            for (subject, payload) in messages {
+                client
+                    .publish(subject.clone(), payload.into())
+                    .await
+                    .with_context(|| format!("Failed to publish to '{subject}'"))?;
+            }
+            client
+                .flush()
+                .await
+                .context("Failed to flush messages to NATS server")?;
+        }
+
+        log::debug!("Published {} record(s) to NATS", data.len());
+        Ok(())
+    }
+}
@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use async_trait::async_trait;
+use serde_json::{Map, Value};
+use sqlx::PgPool;
+use sqlx::postgres::PgPoolOptions;
+use sqlx::types::Json as SqlJson;
+use tokio::sync::OnceCell;
+
+use crate::schemas::kafka::KafkaMessage;
+use crate::schemas::{Json, json_path};
+use crate::secret::Secret;
+use crate::sql::{quote_ident, quote_table};
+use crate::writers::Writer;
+
+const DEFAULT_MAX_CONNECTIONS: u32 = 5;
+/// Records sent per statement. A batch larger than this is still written in
+/// a single transaction.
+const CHUNK_SIZE: usize = 1000;
+
+/// Inserts records into a PostgreSQL table, or upserts them on a conflict
+/// key. Fields are converted to the column types by PostgreSQL, as with
+/// `jsonb_populate_record`, so e.g. RFC 3339 strings land in `timestamptz`
+/// columns and objects in `jsonb` columns.
+///
+/// Without an explicit mapping, every top-level field named like a column of
+/// the table is written to it. Columns no record of a batch has keep their
+/// default; a record missing a field that others have gets `NULL`.
+pub struct PostgresWriter<T: Json> {
+    pool: PgPool,
+    table: String,
+    columns: Vec<(String, String)>,
+    upsert_key: Vec<String>,
+    table_columns: OnceCell<Vec<String>>,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Json> PostgresWriter<T> {
+    pub fn builder(url: impl Into<Secret>, table: &str) -> PostgresWriterBuilder<T> {
+        PostgresWriterBuilder::new(url, table)
+    }
+}
+
+/// Builds a [`PostgresWriter`]. Connections are opened when first needed and
+/// kept in a pool.
+pub struct PostgresWriterBuilder<T: Json> {
+    url: Secret,
+    table: String,
+    columns: Vec<(String, String)>,
+    upsert_key: Vec<String>,
+    max_connections: u32,
+    connect_timeout: Option<Duration>,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Json> PostgresWriterBuilder<T> {
+    fn new(url: impl Into<Secret>, table: &str) -> Self {
+        Self {
+            url: url.into(),
+            table: table.into(),
+            columns: Vec::new(),
+            upsert_key: Vec::new(),
+            max_connections: DEFAULT_MAX_CONNECTIONS,
+            connect_timeout: None,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    /// Writes the field at `field`, a dotted path such as `user.id`, to
+    /// `column`. Once a column is mapped, only mapped columns are written.
+    pub fn with_column(mut self, column: &str, field: &str) -> Self {
+        self.columns.push((column.into(), field.into()));
+        self
+    }
+
+    /// Updates the existing row when a record has the same values in these
+    /// columns, which must have a unique index.
+    pub fn with_upsert(mut self, key: Vec<String>) -> Self {
+        self.upsert_key = key;
+        self
+    }
+
+    /// Size of the connection pool. Defaults to 5.
+    pub fn with_max_connections(mut self, max_connections: u32) -> Self {
+        self.max_connections = max_connections;
+        self
+    }
+
+    /// Maximum time to wait for a connection from the pool.
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    pub fn build(self) -> Result<PostgresWriter<T>> {
+        if self.table.is_empty() {
+            bail!("PostgreSQL writer needs a table");
+        }
+        if !self.columns.is_empty()
+            && let Some(column) = self
+                .upsert_key
+                .iter()
+                .find(|key| !self.columns.iter().any(|(column, _)| column == *key))
+        {
+            bail!("Upsert key column '{column}' is not mapped to a field");
+        }
+
+        let url = self
+            .url
+            .resolve()
+            .context("Failed to resolve PostgreSQL URL")?;
+        let mut options = PgPoolOptions::new().max_connections(self.max_connections);
+        if let Some(timeout) = self.connect_timeout {
+            options = options.acquire_timeout(timeout);
+        }
+        let pool = options
+            .connect_lazy(&url)
+            .with_context(|| format!("Invalid PostgreSQL URL for table '{}'", self.table))?;
+
+        Ok(PostgresWriter {
+            pool,
+            table: quote_table(&self.table),
+            columns: self.columns,
+            upsert_key: self.upsert_key,
+            table_columns: OnceCell::new(),
+            _marker: std::marker::PhantomData,
+        })
+    }
+}
+
+impl<T: Json> PostgresWriter<T> {
+    /// Columns of the table, in order, looked up on first use.
+    async fn table_columns(&self) -> Result<&[String]> {
+        let columns = self
+            .table_columns
+            .get_or_try_init(|| async {
+                sqlx::query_scalar(
+                    "SELECT attname::text FROM pg_attribute \
+                     WHERE attrelid = $1::regclass AND attnum > 0 AND NOT attisdropped \
+                     ORDER BY attnum",
+                )
+                .bind(&self.table)
+                .fetch_all(&self.pool)
+                .await
+                .with_context(|| format!("Failed to look up columns of {}", self.table))
+            })
+            .await?;
+        Ok(columns)
+    }
+
+    /// Turns records into rows keyed by column, along with the columns to
+    /// write.
+    async fn rows(&self, values: Vec<Value>) -> Result<(Vec<String>, Vec<Map<String, Value>>)> {
+        if !self.columns.is_empty() {
+            let rows = values
+                .iter()
+                .map(|value| {
+                    self.columns
+                        .iter()
+                        .map(|(column, field)| {
+                            let field = json_path(value, field).cloned().unwrap_or(Value::Null);
+                            (column.clone(), field)
+                        })
+                        .collect()
+                })
+                .collect();
+            let columns = self.columns.iter().map(|(column, _)| column.clone());
+            return Ok((columns.collect(), rows));
+        }
+
+        let table_columns = self.table_columns().await?;
+        let rows = values
+            .into_iter()
+            .map(|value| match value {
+                Value::Object(mut record) => {
+                    record.retain(|field, _| table_columns.contains(field));
+                    Ok(record)
+                }
+                other => bail!("Cannot write {other} to {}, expected an object", self.table),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let columns = table_columns
+            .iter()
+            .filter(|column| rows.iter().any(|row| row.contains_key(*column)))
+            .cloned()
+            .collect();
+        Ok((columns, rows))
+    }
+
+    /// Keeps the last of several rows with the same upsert key, as PostgreSQL
+    /// refuses to update a row twice in one statement.
+    fn dedup(&self, rows: Vec<Map<String, Value>>) -> Vec<Map<String, Value>> {
+        if self.upsert_key.is_empty() {
+            return rows;
+        }
+        let key = |row: &Map<String, Value>| {
+            self.upsert_key
+                .iter()
+                .map(|column| row.get(column).cloned().unwrap_or(Value::Null))
+                .collect::<Vec<_>>()
+        };
+
+        let mut last: HashMap<String, usize> = HashMap::new();
+        for (i, row) in rows.iter().enumerate() {
+            last.insert(Value::Array(key(row)).to_string(), i);
+        }
+        rows.into_iter()
+            .enumerate()
+            .filter(|(i, row)| last.get(&Value::Array(key(row)).to_string()) == Some(i))
+            .map(|(_, row)| row)
+            .collect()
+    }
+
+    fn statement(&self, columns: &[String]) -> String {
+        let list = columns
+            .iter()
+            .map(|column| quote_ident(column))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut sql = format!(
+            "INSERT INTO {table} ({list}) SELECT {list} FROM jsonb_populate_recordset(NULL::{table}, $1)",
+            table = self.table
+        );
+
+        if !self.upsert_key.is_empty() {
+            let key = self
+                .upsert_key
+                .iter()
+                .map(|column| quote_ident(column))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let updates = columns
+                .iter()
+                .filter(|column| !self.upsert_key.contains(column))
+                .map(|column| format!("{0} = EXCLUDED.{0}", quote_ident(column)))
+                .collect::<Vec<_>>();
+            match updates.is_empty() {
+                true => sql.push_str(&format!(" ON CONFLICT ({key}) DO NOTHING")),
+                false => sql.push_str(&format!(
+                    " ON CONFLICT ({key}) DO UPDATE SET {}",
+                    updates.join(", ")
+                )),
+            }
+        }
+        sql
+    }
+}
+
+#[async_trait]
+impl<T: Json> Writer for PostgresWriter<T> {
+    type Item = KafkaMessage<T>;
+
+    async fn write(&self, data: KafkaMessage<T>) -> Result<()> {
+        self.write_batch(vec![data]).await
+    }
+
+    async fn write_batch(&self, data: Vec<KafkaMessage<T>>) -> Result<()> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        let values = data
+            .into_iter()
+            .map(|message| serde_json::to_value(message.value))
+            .collect::<serde_json::Result<_>>()?;
+        let (columns, rows) = self.rows(values).await?;
+        if columns.is_empty() {
+            bail!("No field of the records matches a column of {}", self.table);
+        }
+        let rows = self.dedup(rows);
+        let sql = self.statement(&columns);
+        log::trace!("Writing to {} with: {sql}", self.table);
+
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .with_context(|| format!("Failed to connect to PostgreSQL for {}", self.table))?;
+        for chunk in rows.chunks(CHUNK_SIZE) {
+            sqlx::query(&sql)
+                .bind(SqlJson(chunk))
+                .execute(&mut *transaction)
+                .await
+                .with_context(|| format!("Failed to write to {}", self.table))?;
+        }
+        transaction.commit().await?;
+
+        log::debug!("Wrote {} record(s) to {}", rows.len(), self.table);
+        Ok(())
+    }
+}
@@ -0,0 +1,191 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use async_trait::async_trait;
+use redis::{Cmd, Pipeline};
+use serde_json::json;
+
+use crate::redis::RedisConnection;
+use crate::schemas::Json;
+use crate::schemas::kafka::KafkaMessage;
+use crate::secret::Secret;
+use crate::template::Template;
+use crate::writers::Writer;
+
+const DEFAULT_FIELD: &str = "data";
+
+/// The command a [`RedisWriter`] sends for each record.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RedisCommand {
+    /// Appends an entry to a stream, with the record in one field.
+    #[default]
+    Xadd,
+    /// Pushes onto the head of a list.
+    Lpush,
+    /// Pushes onto the tail of a list.
+    Rpush,
+    /// Publishes to a channel.
+    Publish,
+    /// Stores the record under a key, replacing any previous value.
+    Set,
+}
+
+/// Writes records to Redis as JSON. The key, i.e. the stream, list, channel
+/// or key name, is a [`Template`] rendered with each record as `{key}` and
+/// `{value...}`, e.g. `user:{value.id}`.
+///
+/// A batch is sent in one `MULTI`/`EXEC` transaction.
+pub struct RedisWriter<T: Json> {
+    connection: RedisConnection,
+    command: RedisCommand,
+    key: Template,
+    field: String,
+    max_len: Option<usize>,
+    ttl: Option<Duration>,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Json> RedisWriter<T> {
+    pub fn builder(
+        url: impl Into<Secret>,
+        command: RedisCommand,
+        key: &str,
+    ) -> RedisWriterBuilder<T> {
+        RedisWriterBuilder::new(url, command, key)
+    }
+}
+
+/// Builds a [`RedisWriter`].
+pub struct RedisWriterBuilder<T: Json> {
+    url: Secret,
+    command: RedisCommand,
+    key: String,
+    field: String,
+    max_len: Option<usize>,
+    ttl: Option<Duration>,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Json> RedisWriterBuilder<T> {
+    fn new(url: impl Into<Secret>, command: RedisCommand, key: &str) -> Self {
+        Self {
+            url: url.into(),
+            command,
+            key: key.into(),
+            field: DEFAULT_FIELD.into(),
+            max_len: None,
+            ttl: None,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    /// Stream field holding the record with `XADD`. Defaults to `data`.
+    pub fn with_field(mut self, field: &str) -> Self {
+        self.field = field.into();
+        self
+    }
+
+    /// Trims the stream to about this many entries with `XADD`.
+    pub fn with_max_len(mut self, max_len: usize) -> Self {
+        self.max_len = Some(max_len);
+        self
+    }
+
+    /// Expires keys written with `SET` after this long.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    pub fn build(self) -> Result<RedisWriter<T>> {
+        if self.key.is_empty() {
+            bail!("Redis writer needs a key");
+        }
+        if self.max_len.is_some() && self.command != RedisCommand::Xadd {
+            bail!("A maximum length only applies to XADD");
+        }
+        match self.ttl {
+            Some(_) if self.command != RedisCommand::Set => bail!("A TTL only applies to SET"),
+            Some(ttl) if ttl.as_millis() == 0 => bail!("Redis TTL must be at least 1ms"),
+            _ => {}
+        }
+
+        let url = self.url.resolve().context("Failed to resolve Redis URL")?;
+        let key = Template::parse(&self.key).context("Invalid Redis key")?;
+
+        Ok(RedisWriter {
+            connection: RedisConnection::open(&url)?,
+            command: self.command,
+            key,
+            field: self.field,
+            max_len: self.max_len,
+            ttl: self.ttl,
+            _marker: std::marker::PhantomData,
+        })
+    }
+}
+
+impl<T: Json> RedisWriter<T> {
+    fn command(&self, message: &KafkaMessage<T>) -> Result<Cmd> {
+        let payload = serde_json::to_string(&message.value)?;
+        let key = self
+            .key
+            .render(&json!({ "key": message.key, "value": message.value }));
+        if key.is_empty() {
+            bail!("Redis key '{}' rendered empty", self.key);
+        }
+
+        let mut cmd = match self.command {
+            RedisCommand::Xadd => {
+                let mut cmd = redis::cmd("XADD");
+                cmd.arg(&key);
+                if let Some(max_len) = self.max_len {
+                    cmd.arg("MAXLEN").arg("~").arg(max_len);
+                }
+                cmd.arg("*").arg(&self.field);
+                cmd
+            }
+            RedisCommand::Lpush => redis::cmd("LPUSH"),
+            RedisCommand::Rpush => redis::cmd("RPUSH"),
+            RedisCommand::Publish => redis::cmd("PUBLISH"),
+            RedisCommand::Set => redis::cmd("SET"),
+        };
+        if self.command != RedisCommand::Xadd {
+            cmd.arg(&key);
+        }
+        cmd.arg(payload);
+        if let Some(ttl) = self.ttl {
+            cmd.arg("PX").arg(ttl.as_millis() as u64);
+        }
+        Ok(cmd)
+    }
+}
+
+#[async_trait]
+impl<T: Json> Writer for RedisWriter<T> {
+    type Item = KafkaMessage<T>;
+
+    async fn write(&self, data: KafkaMessage<T>) -> Result<()> {
+        self.write_batch(vec![data]).await
+    }
+
+    async fn write_batch(&self, data: Vec<KafkaMessage<T>>) -> Result<()> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        let mut pipeline = Pipeline::new();
+        pipeline.atomic();
+        for message in &data {
+            pipeline.add_command(self.command(message)?).ignore();
+        }
+
+        let mut connection = self.connection.get().await?;
+        pipeline
+            .query_async::<()>(&mut connection)
+            .await
+            .with_context(|| format!("Failed to write to Redis with {:?}", self.command))?;
+
+        log::debug!("Wrote {} record(s) with {:?}", data.len(), self.command);
+        Ok(())
+    }
+}
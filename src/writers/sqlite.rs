@@ -0,0 +1,256 @@
+use std::str::FromStr;
+
+use anyhow::{Context, Result, bail};
+use async_trait::async_trait;
+use serde_json::{Map, Value};
+use sqlx::SqlitePool;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use tokio::sync::Mutex;
+
+use crate::schemas::Json;
+use crate::schemas::kafka::KafkaMessage;
+use crate::sql::quote_ident;
+use crate::writers::Writer;
+
+/// Inserts records into a SQLite table, or upserts them on a key. Top-level
+/// fields are written to the columns of the same name; a record missing a
+/// column's field writes `NULL`, and objects and arrays are stored as JSON
+/// text.
+///
+/// The database file is created if needed, and so is the table: with the
+/// declared columns, or with one column per field of the first record, typed
+/// after its value.
+pub struct SqliteWriter<T: Json> {
+    pool: SqlitePool,
+    table: String,
+    declared: Vec<(String, String)>,
+    upsert_key: Vec<String>,
+    /// Columns of the table, known once it has been looked up or created.
+    columns: Mutex<Option<Vec<String>>>,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Json> SqliteWriter<T> {
+    pub fn builder(path: &str, table: &str) -> SqliteWriterBuilder<T> {
+        SqliteWriterBuilder::new(path, table)
+    }
+}
+
+/// Builds a [`SqliteWriter`]. The path may also be a `sqlite:` URL.
+pub struct SqliteWriterBuilder<T: Json> {
+    path: String,
+    table: String,
+    declared: Vec<(String, String)>,
+    upsert_key: Vec<String>,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Json> SqliteWriterBuilder<T> {
+    fn new(path: &str, table: &str) -> Self {
+        Self {
+            path: path.into(),
+            table: table.into(),
+            declared: Vec::new(),
+            upsert_key: Vec::new(),
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    /// Declares a column of the table, e.g. `("amount", "REAL")`, used when
+    /// the table does not exist yet.
+    pub fn with_column(mut self, name: &str, sql_type: &str) -> Self {
+        self.declared.push((name.into(), sql_type.into()));
+        self
+    }
+
+    /// Updates the existing row when a record has the same values in these
+    /// columns. A table created by the writer gets a unique constraint on
+    /// them.
+    pub fn with_upsert(mut self, key: Vec<String>) -> Self {
+        self.upsert_key = key;
+        self
+    }
+
+    pub fn build(self) -> Result<SqliteWriter<T>> {
+        if self.table.is_empty() {
+            bail!("SQLite writer needs a table");
+        }
+        if !self.declared.is_empty()
+            && let Some(column) = self
+                .upsert_key
+                .iter()
+                .find(|key| !self.declared.iter().any(|(name, _)| name == *key))
+        {
+            bail!("Upsert key column '{column}' is not declared");
+        }
+
+        let options = match self.path.starts_with("sqlite:") {
+            true => SqliteConnectOptions::from_str(&self.path)
+                .with_context(|| format!("Invalid SQLite URL '{}'", self.path))?,
+            false => SqliteConnectOptions::new().filename(&self.path),
+        };
+        // SQLite allows one writer at a time.
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_lazy_with(options.create_if_missing(true));
+
+        Ok(SqliteWriter {
+            pool,
+            table: self.table,
+            declared: self.declared,
+            upsert_key: self.upsert_key,
+            columns: Mutex::new(None),
+            _marker: std::marker::PhantomData,
+        })
+    }
+}
+
+/// Column type for a field of the first record. `NULL` leaves the column
+/// untyped.
+fn infer_type(value: &Value) -> &'static str {
+    match value {
+        Value::Bool(_) => "INTEGER",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "INTEGER",
+        Value::Number(_) => "REAL",
+        Value::String(_) | Value::Array(_) | Value::Object(_) => "TEXT",
+        Value::Null => "",
+    }
+}
+
+impl<T: Json> SqliteWriter<T> {
+    /// Returns the columns of the table, creating it if it does not exist.
+    async fn ensure_table(&self, first: &Map<String, Value>) -> Result<Vec<String>> {
+        let mut columns = self.columns.lock().await;
+        if let Some(columns) = columns.as_ref() {
+            return Ok(columns.clone());
+        }
+
+        let existing: Vec<String> =
+            sqlx::query_scalar("SELECT name FROM pragma_table_info(?) ORDER BY cid")
+                .bind(&self.table)
+                .fetch_all(&self.pool)
+                .await
+                .with_context(|| format!("Failed to open SQLite table '{}'", self.table))?;
+        if !existing.is_empty() {
+            return Ok(columns.insert(existing).clone());
+        }
+
+        let definitions: Vec<(String, String)> = match self.declared.is_empty() {
+            false => self.declared.clone(),
+            true => first
+                .iter()
+                .map(|(name, value)| (name.clone(), infer_type(value).to_string()))
+                .collect(),
+        };
+        if definitions.is_empty() {
+            bail!(
+                "Cannot create SQLite table '{}' without columns",
+                self.table
+            );
+        }
+
+        let mut parts: Vec<String> = definitions
+            .iter()
+            .map(|(name, sql_type)| format!("{} {sql_type}", quote_ident(name)).trim().into())
+            .collect();
+        if !self.upsert_key.is_empty() {
+            let key: Vec<String> = self.upsert_key.iter().map(|k| quote_ident(k)).collect();
+            parts.push(format!("UNIQUE ({})", key.join(", ")));
+        }
+        let sql = format!(
+            "CREATE TABLE IF NOT EXISTS {} ({})",
+            quote_ident(&self.table),
+            parts.join(", ")
+        );
+        sqlx::query(&sql)
+            .execute(&self.pool)
+            .await
+            .with_context(|| format!("Failed to create SQLite table '{}'", self.table))?;
+        log::info!("Created SQLite table '{}'", self.table);
+
+        let created = definitions.into_iter().map(|(name, _)| name).collect();
+        Ok(columns.insert(created).clone())
+    }
+
+    fn statement(&self, columns: &[String]) -> String {
+        let list: Vec<String> = columns.iter().map(|column| quote_ident(column)).collect();
+        let placeholders = vec!["?"; columns.len()].join(", ");
+        let mut sql = format!(
+            "INSERT INTO {} ({}) VALUES ({placeholders})",
+            quote_ident(&self.table),
+            list.join(", ")
+        );
+
+        if !self.upsert_key.is_empty() {
+            let key: Vec<String> = self.upsert_key.iter().map(|k| quote_ident(k)).collect();
+            let updates: Vec<String> = columns
+                .iter()
+                .filter(|column| !self.upsert_key.contains(column))
+                .map(|column| format!("{0} = excluded.{0}", quote_ident(column)))
+                .collect();
+            match updates.is_empty() {
+                true => sql.push_str(&format!(" ON CONFLICT ({}) DO NOTHING", key.join(", "))),
+                false => sql.push_str(&format!(
+                    " ON CONFLICT ({}) DO UPDATE SET {}",
+                    key.join(", "),
+                    updates.join(", ")
+                )),
+            }
+        }
+        sql
+    }
+}
+
+#[async_trait]
+impl<T: Json> Writer for SqliteWriter<T> {
+    type Item = KafkaMessage<T>;
+
+    async fn write(&self, data: KafkaMessage<T>) -> Result<()> {
+        self.write_batch(vec![data]).await
+    }
+
+    async fn write_batch(&self, data: Vec<KafkaMessage<T>>) -> Result<()> {
+        let records = data
+            .into_iter()
+            .map(|message| match serde_json::to_value(message.value)? {
+                Value::Object(record) => Ok(record),
+                other => bail!(
+                    "Cannot write {other} to '{}', expected an object",
+                    self.table
+                ),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let Some(first) = records.first() else {
+            return Ok(());
+        };
+
+        let columns = self.ensure_table(first).await?;
+        let sql = self.statement(&columns);
+        log::trace!("Writing to '{}' with: {sql}", self.table);
+
+        let mut transaction = self.pool.begin().await?;
+        for record in &records {
+            let mut query = sqlx::query(&sql);
+            for column in &columns {
+                query = match record.get(column) {
+                    None | Some(Value::Null) => query.bind(None::<String>),
+                    Some(Value::Bool(b)) => query.bind(*b),
+                    Some(Value::Number(n)) => match n.as_i64() {
+                        Some(n) => query.bind(n),
+                        None => query.bind(n.as_f64()),
+                    },
+                    Some(Value::String(s)) => query.bind(s.as_str()),
+                    Some(other) => query.bind(other.to_string()),
+                };
+            }
+            query
+                .execute(&mut *transaction)
+                .await
+                .with_context(|| format!("Failed to write to SQLite table '{}'", self.table))?;
+        }
+        transaction.commit().await?;
+
+        log::debug!("Wrote {} record(s) to '{}'", records.len(), self.table);
+        Ok(())
+    }
+}
@@ -0,0 +1,113 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use tokio::io::{AsyncWriteExt, Stdout};
+use tokio::sync::Mutex;
+
+use crate::schemas::Json;
+use crate::schemas::kafka::KafkaMessage;
+use crate::writers::Writer;
+
+/// Prints records to standard output as JSON, one per line unless pretty
+/// printed. Logs go to standard error, so the output can be piped on.
+pub struct StdoutWriter<T: Json> {
+    pretty: bool,
+    key_prefix: bool,
+    stdout: Mutex<Stdout>,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Json> StdoutWriter<T> {
+    pub fn new() -> Self {
+        Self::builder().build().expect("Invalid stdout writer")
+    }
+
+    pub fn builder() -> StdoutWriterBuilder<T> {
+        StdoutWriterBuilder::new()
+    }
+}
+
+impl<T: Json> Default for StdoutWriter<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Builds a [`StdoutWriter`]. Records are printed as compact JSON without
+/// their key unless configured otherwise.
+pub struct StdoutWriterBuilder<T: Json> {
+    pretty: bool,
+    key_prefix: bool,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Json> StdoutWriterBuilder<T> {
+    fn new() -> Self {
+        Self {
+            pretty: false,
+            key_prefix: false,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    /// Prints each record as indented JSON over several lines.
+    pub fn with_pretty(mut self) -> Self {
+        self.pretty = true;
+        self
+    }
+
+    /// Prints the key of each record and a tab before it.
+    pub fn with_key_prefix(mut self) -> Self {
+        self.key_prefix = true;
+        self
+    }
+
+    pub fn build(self) -> Result<StdoutWriter<T>> {
+        Ok(StdoutWriter {
+            pretty: self.pretty,
+            key_prefix: self.key_prefix,
+            stdout: Mutex::new(tokio::io::stdout()),
+            _marker: std::marker::PhantomData,
+        })
+    }
+}
+
+impl<T: Json> StdoutWriter<T> {
+    fn format(&self, message: &KafkaMessage<T>, out: &mut Vec<u8>) -> Result<()> {
+        if self.key_prefix {
+            out.extend_from_slice(message.key.as_bytes());
+            out.push(b'\t');
+        }
+        match self.pretty {
+            true => serde_json::to_writer_pretty(&mut *out, &message.value)?,
+            false => serde_json::to_writer(&mut *out, &message.value)?,
+        }
+        out.push(b'\n');
+        Ok(())
+    }
+
+    async fn print(&self, out: Vec<u8>) -> Result<()> {
+        let mut stdout = self.stdout.lock().await;
+        stdout.write_all(&out).await?;
+        stdout.flush().await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<T: Json> Writer for StdoutWriter<T> {
+    type Item = KafkaMessage<T>;
+
+    async fn write(&self, data: KafkaMessage<T>) -> Result<()> {
+        let mut out = Vec::new();
+        self.format(&data, &mut out)?;
+        self.print(out).await
+    }
+
+    async fn write_batch(&self, data: Vec<KafkaMessage<T>>) -> Result<()> {
+        let mut out = Vec::new();
+        for message in &data {
+            self.format(message, &mut out)?;
+        }
+        self.print(out).await
+    }
+}